@@ -0,0 +1,354 @@
+use std::{env, path::PathBuf, time::Duration};
+
+use macroquad::color::Color;
+
+use crate::{
+    Config, PROGRAM_START, TraceFormat, WaitKeyPolicy,
+    memory::MemorySize,
+    window::{RenderConfig, THEMES},
+};
+
+#[derive(Debug, Clone)]
+pub struct Args {
+    pub config: Config,
+    pub render: RenderConfig,
+    pub roms: Vec<PathBuf>,
+    pub playlist_slot: Duration,
+    pub start: usize,
+    pub speed: Option<f64>,
+    pub ipf: Option<u32>,
+    pub sound_hz: Option<f64>,
+    pub volume: Option<f32>,
+    pub pause_at_start: bool,
+    pub registers: Vec<(u8, u8)>,
+    pub pokes: Vec<(usize, u8)>,
+    pub sprites: Vec<(usize, PathBuf)>,
+    pub disassemble: bool,
+    pub analyze: bool,
+    pub memory_map: bool,
+    pub memory_map_at: Option<usize>,
+    pub hotkeys: Option<PathBuf>,
+    pub keymap: Option<PathBuf>,
+    pub prefs: Option<PathBuf>,
+    pub capture: bool,
+    pub headless: Option<usize>,
+    pub selftest: bool,
+    pub demo: bool,
+    pub frames: u32,
+    pub out: PathBuf,
+    pub fault_dir: Option<PathBuf>,
+    pub state: Option<PathBuf>,
+    pub log_events: bool,
+    pub watch: bool,
+    pub mute: bool,
+}
+
+impl Default for Args {
+    fn default() -> Self {
+        Self {
+            config: Config::default(),
+            render: RenderConfig::default(),
+            roms: vec![],
+            playlist_slot: Duration::from_secs(30),
+            start: PROGRAM_START,
+            speed: None,
+            ipf: None,
+            sound_hz: None,
+            volume: None,
+            pause_at_start: false,
+            registers: vec![],
+            pokes: vec![],
+            sprites: vec![],
+            disassemble: false,
+            analyze: false,
+            memory_map: false,
+            memory_map_at: None,
+            hotkeys: None,
+            keymap: None,
+            prefs: None,
+            capture: false,
+            headless: None,
+            selftest: false,
+            demo: false,
+            frames: 60,
+            out: "capture.png".into(),
+            fault_dir: None,
+            state: None,
+            log_events: false,
+            watch: false,
+            mute: false,
+        }
+    }
+}
+
+pub fn parse() -> Result<Args, String> {
+    let mut args = Args::default();
+    let mut iter = env::args().skip(1);
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--playlist-secs" => {
+                let secs = iter
+                    .next()
+                    .and_then(|s| s.parse::<f64>().ok())
+                    .filter(|s| *s > 0.0)
+                    .ok_or("--playlist-secs expects a positive number of seconds")?;
+                args.playlist_slot = Duration::from_secs_f64(secs);
+            }
+            "--start" => {
+                args.start = iter
+                    .next()
+                    .and_then(|s| parse_number(&s))
+                    .ok_or("--start expects an address like 0x200")?;
+            }
+            "--speed" => {
+                let ips = iter
+                    .next()
+                    .and_then(|s| s.parse::<f64>().ok())
+                    .filter(|s| *s > 0.0)
+                    .ok_or("--speed expects a positive number of instructions per second")?;
+                args.speed = Some(ips);
+            }
+            "--ipf" => {
+                let ipf = iter
+                    .next()
+                    .and_then(|s| s.parse::<u32>().ok())
+                    .filter(|s| *s > 0)
+                    .ok_or("--ipf expects a positive number of instructions per frame")?;
+                args.ipf = Some(ipf);
+            }
+            "--sound-hz" => {
+                let hz = iter
+                    .next()
+                    .and_then(|s| s.parse::<f64>().ok())
+                    .filter(|s| *s > 0.0)
+                    .ok_or("--sound-hz expects a positive rate in Hz")?;
+                args.sound_hz = Some(hz);
+            }
+            "--reg" => {
+                let reg = iter
+                    .next()
+                    .and_then(|s| parse_register_assignment(&s))
+                    .ok_or("--reg expects an assignment like V3=0x20")?;
+                args.registers.push(reg);
+            }
+            "--poke" => {
+                let poke = iter
+                    .next()
+                    .and_then(|s| parse_poke(&s))
+                    .ok_or("--poke expects an assignment like 0x300=0xFF")?;
+                args.pokes.push(poke);
+            }
+            "--sprite" => {
+                let sprite = iter
+                    .next()
+                    .and_then(|s| parse_sprite(&s))
+                    .ok_or("--sprite expects an address and a png like 0x300=ship.png")?;
+                args.sprites.push(sprite);
+            }
+            "--disassemble" => args.disassemble = true,
+            "--analyze" => args.analyze = true,
+            "--memory-map" => args.memory_map = true,
+            "--memory-map-at" => {
+                let addr = iter
+                    .next()
+                    .and_then(|s| parse_number(&s))
+                    .ok_or("--memory-map-at expects an address like 0x200")?;
+                args.memory_map = true;
+                args.memory_map_at = Some(addr);
+            }
+            "--capture" => args.capture = true,
+            "--headless" => {
+                let instrs = iter
+                    .next()
+                    .and_then(|s| s.parse().ok())
+                    .ok_or("--headless expects a number of instructions to run")?;
+                args.headless = Some(instrs);
+            }
+            "--selftest" => args.selftest = true,
+            "--demo" => args.demo = true,
+            "--frames" => {
+                args.frames = iter
+                    .next()
+                    .and_then(|s| s.parse().ok())
+                    .ok_or("--frames expects a number of frames")?;
+            }
+            "--out" => args.out = iter.next().ok_or("--out expects a path")?.into(),
+            "--fault-dir" => {
+                let dir = iter
+                    .next()
+                    .ok_or("--fault-dir expects a directory for fault reports")?;
+                args.fault_dir = Some(dir.into());
+            }
+            "--state" => {
+                let path = iter
+                    .next()
+                    .ok_or("--state expects a path to keep the snapshot slot in")?;
+                args.state = Some(path.into());
+            }
+            "--hotkeys" => {
+                let path = iter
+                    .next()
+                    .ok_or("--hotkeys expects a path to a toml file")?;
+                args.hotkeys = Some(path.into());
+            }
+            "--keymap" => {
+                let path = iter
+                    .next()
+                    .ok_or("--keymap expects a path to a toml file")?;
+                args.keymap = Some(path.into());
+            }
+            "--prefs" => {
+                let path = iter.next().ok_or("--prefs expects a path to a toml file")?;
+                args.prefs = Some(path.into());
+            }
+            "--watch" => args.watch = true,
+            "--mute" => args.mute = true,
+            "--volume" => {
+                let volume = iter
+                    .next()
+                    .and_then(|s| s.parse::<f32>().ok())
+                    .filter(|v| (0.0..=1.0).contains(v))
+                    .ok_or("--volume expects a number from 0 to 1")?;
+                args.volume = Some(volume);
+            }
+            "--pause-at-start" => args.pause_at_start = true,
+            // exact pixels drawn no faster than the COSMAC VIP could, or
+            // less flicker at the cost of both
+            "--accuracy" => {
+                args.config.display_wait = true;
+                args.render.ghosting = false;
+            }
+            "--smooth" => {
+                args.config.display_wait = false;
+                args.render.ghosting = true;
+            }
+            "--crt-dots" => args.render.crt_dots = true,
+            "--border" => {
+                args.render.border = iter
+                    .next()
+                    .and_then(|s| parse_color(&s))
+                    .ok_or("--border expects a color like 202020")?;
+            }
+            "--palette" => {
+                args.render.palette = iter
+                    .next()
+                    .and_then(|s| parse_palette(&s))
+                    .ok_or("--palette expects four colors like 000000,FFFFFF,AAAAAA,555555")?;
+            }
+            "--theme" => {
+                let name = iter.next();
+                args.render.palette = THEMES
+                    .iter()
+                    .find(|(theme, _)| Some(*theme) == name.as_deref())
+                    .map(|(_, palette)| *palette)
+                    .ok_or("--theme expects white, green or amber")?;
+            }
+            "--random-init" => {
+                let seed = iter
+                    .next()
+                    .and_then(|s| s.parse().ok())
+                    .ok_or("--random-init expects a seed")?;
+                args.config.random_init_seed = Some(seed);
+            }
+            "--seed" => {
+                let seed = iter
+                    .next()
+                    .and_then(|s| s.parse().ok())
+                    .ok_or("--seed expects a number")?;
+                args.config.rng_seed = Some(seed);
+            }
+            "--time-instrs" => args.config.debug_time_instrs = true,
+            "--strict-memory" => args.config.strict_memory_bounds = true,
+            "--trace" => args.config.debug_print_instrs = true,
+            "--log-vf" => args.config.debug_log_vf = true,
+            "--log-pc-range" => args.config.debug_log_pc_range = true,
+            "--log-events" => args.log_events = true,
+            "--trace-format" => {
+                args.config.trace_format = match iter.next().as_deref() {
+                    Some("text") => TraceFormat::Text,
+                    Some("csv") => TraceFormat::Csv,
+                    _ => return Err("--trace-format expects text or csv".into()),
+                };
+            }
+            // the three distinct 8XY6/8XYE behaviours, see Chip8::shift
+            "--shift" => {
+                (args.config.bitshift_copies_y, args.config.shift_vf_from_vx) =
+                    match iter.next().as_deref() {
+                        Some("vx") => (false, false),
+                        Some("vy") => (true, false),
+                        Some("vy-flag-vx") => (true, true),
+                        _ => return Err("--shift expects vx, vy or vy-flag-vx".into()),
+                    };
+            }
+            "--wait-key" => {
+                args.config.wait_key_policy = match iter.next().as_deref() {
+                    Some("lowest") => WaitKeyPolicy::Lowest,
+                    Some("newest") => WaitKeyPolicy::Newest,
+                    Some("one") => WaitKeyPolicy::OnlyOne,
+                    _ => return Err("--wait-key expects lowest, newest or one".into()),
+                };
+            }
+            "--rewind" => {
+                args.config.rewind_frames = iter
+                    .next()
+                    .and_then(|s| s.parse().ok())
+                    .ok_or("--rewind expects a number of frames to keep")?;
+            }
+            "--wrap-sprites" => args.config.sprite_wrapping = true,
+            "--fast-boot" => args.config.fast_boot = true,
+            "--strict-keys" => args.config.strict_key_index = true,
+            "--strict-alignment" => args.config.strict_pc_alignment = true,
+            "--lenient" => args.config.lenient_opcodes = true,
+            "--extended-memory" => args.config.memory_size = MemorySize::Extended,
+            flag if flag.starts_with("--") => return Err(format!("unknown flag {flag}")),
+            rom => args.roms.push(rom.into()),
+        }
+    }
+    Ok(args)
+}
+
+pub fn parse_number(s: &str) -> Option<usize> {
+    match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        Some(hex) => usize::from_str_radix(hex, 16).ok(),
+        None => s.parse().ok(),
+    }
+}
+
+fn parse_byte(s: &str) -> Option<u8> {
+    parse_number(s)?.try_into().ok()
+}
+
+// "V3=0x20"
+pub fn parse_register_assignment(s: &str) -> Option<(u8, u8)> {
+    let (reg, value) = s.split_once('=')?;
+    let reg = reg.strip_prefix(['V', 'v'])?;
+    let reg = u8::from_str_radix(reg, 16).ok().filter(|r| *r < 0x10)?;
+    Some((reg, parse_byte(value)?))
+}
+
+// "0x300=0xFF"
+pub fn parse_poke(s: &str) -> Option<(usize, u8)> {
+    let (addr, value) = s.split_once('=')?;
+    Some((parse_number(addr)?, parse_byte(value)?))
+}
+
+// "0x300=ship.png"
+fn parse_sprite(s: &str) -> Option<(usize, PathBuf)> {
+    let (addr, path) = s.split_once('=')?;
+    Some((parse_number(addr)?, path.into()))
+}
+
+// "RRGGBB" or "#RRGGBB"
+pub fn parse_color(s: &str) -> Option<Color> {
+    let hex = s.strip_prefix('#').unwrap_or(s);
+    if hex.len() != 6 {
+        return None;
+    }
+    Some(Color::from_hex(u32::from_str_radix(hex, 16).ok()?))
+}
+
+// four comma separated colors, for pixel values 0 to 3
+fn parse_palette(s: &str) -> Option<[Color; 4]> {
+    let colors = s.split(',').map(parse_color).collect::<Option<Vec<_>>>()?;
+    colors.try_into().ok()
+}
@@ -0,0 +1,85 @@
+use macroquad::audio::{self, PlaySoundParams, Sound};
+
+use crate::tern;
+
+const SAMPLE_RATE: u32 = 44_100;
+// divides the sample rate evenly, so the looped second of tone joins up
+// without a click
+const TONE_HZ: u32 = 441;
+pub const DEFAULT_VOLUME: f32 = 0.2;
+// how much each press of the volume hotkeys changes it by
+pub const VOLUME_STEP: f32 = 0.1;
+
+// a square wave that's always playing and only ever has its volume changed,
+// so a rom flicking the sound timer every frame doesn't restart it each time
+pub struct Beeper {
+    sound: Sound,
+    on: bool,
+    // 0 to 1, what the tone plays at while it's on
+    volume: f32,
+}
+
+impl Beeper {
+    pub async fn new(volume: f32) -> Result<Self, String> {
+        let sound = audio::load_sound_from_bytes(&square_wave())
+            .await
+            .map_err(|e| format!("could not set up sound: {e}"))?;
+        audio::play_sound(
+            &sound,
+            PlaySoundParams {
+                looped: true,
+                volume: 0.0,
+            },
+        );
+        Ok(Self {
+            sound,
+            on: false,
+            volume: volume.clamp(0.0, 1.0),
+        })
+    }
+
+    pub fn set(&mut self, on: bool) {
+        if on != self.on {
+            audio::set_sound_volume(&self.sound, tern!(on, self.volume, 0.0));
+            self.on = on;
+        }
+    }
+
+    pub fn volume(&self) -> f32 {
+        self.volume
+    }
+
+    // takes effect straight away if the tone is already playing
+    pub fn set_volume(&mut self, volume: f32) {
+        self.volume = volume.clamp(0.0, 1.0);
+        if self.on {
+            audio::set_sound_volume(&self.sound, self.volume);
+        }
+    }
+}
+
+// one second of 16-bit mono square wave, as a wav file
+fn square_wave() -> Vec<u8> {
+    let half_period = SAMPLE_RATE / TONE_HZ / 2;
+    let samples =
+        (0..SAMPLE_RATE).map(|n| tern!((n / half_period).is_multiple_of(2), i16::MAX, i16::MIN));
+    let data_len = SAMPLE_RATE * 2;
+
+    let mut wav = vec![];
+    wav.extend(b"RIFF");
+    wav.extend((36 + data_len).to_le_bytes());
+    wav.extend(b"WAVEfmt ");
+    wav.extend(16u32.to_le_bytes());
+    wav.extend(1u16.to_le_bytes()); // pcm
+    wav.extend(1u16.to_le_bytes()); // mono
+    wav.extend(SAMPLE_RATE.to_le_bytes());
+    wav.extend((SAMPLE_RATE * 2).to_le_bytes()); // bytes per second
+    wav.extend(2u16.to_le_bytes()); // bytes per sample
+    wav.extend(16u16.to_le_bytes()); // bits per sample
+    wav.extend(b"data");
+    wav.extend(data_len.to_le_bytes());
+    for sample in samples {
+        wav.extend(sample.to_le_bytes());
+    }
+    wav
+}
@@ -0,0 +1,133 @@
+use std::sync::{Arc, Mutex};
+
+use crate::{
+    Chip8, Config, PROGRAM_START, Shared,
+    keys::Keys,
+    playlist::{Playlist, Rom},
+    screen::Screen,
+    timers::Timers,
+};
+
+// the state other threads need to talk to a running Chip8
+#[derive(Debug, Clone)]
+pub struct Handles {
+    pub screen: Arc<Mutex<Screen>>,
+    pub timers: Arc<Mutex<Timers>>,
+    pub shared: Arc<Mutex<Shared>>,
+    pub keys: Arc<Mutex<Keys>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Chip8Builder {
+    config: Config,
+    rom: Option<Rom>,
+    playlist: Option<Playlist>,
+    start: usize,
+    instrs_per_second: Option<f64>,
+    instrs_per_frame: Option<u32>,
+    sound_hz: Option<f64>,
+    paused: bool,
+}
+
+impl Chip8Builder {
+    pub fn new() -> Self {
+        Self {
+            config: Config::default(),
+            rom: None,
+            playlist: None,
+            start: PROGRAM_START,
+            instrs_per_second: None,
+            instrs_per_frame: None,
+            sound_hz: None,
+            paused: false,
+        }
+    }
+
+    pub fn config(mut self, config: Config) -> Self {
+        self.config = config;
+        self
+    }
+
+    pub fn rom(mut self, rom: Rom) -> Self {
+        self.rom = Some(rom);
+        self
+    }
+
+    // replaces `rom`, the playlist's first entry is loaded instead
+    pub fn playlist(mut self, playlist: Playlist) -> Self {
+        self.playlist = Some(playlist);
+        self
+    }
+
+    // where the rom is loaded and execution begins
+    pub fn start_address(mut self, start: usize) -> Self {
+        self.start = start;
+        self
+    }
+
+    pub fn speed(mut self, instrs_per_second: f64) -> Self {
+        self.instrs_per_second = Some(instrs_per_second);
+        self
+    }
+
+    // takes over from `speed`
+    pub fn instrs_per_frame(mut self, ipf: u32) -> Self {
+        self.instrs_per_frame = Some(ipf);
+        self
+    }
+
+    // how often the sound timer counts down, 60Hz like the delay timer if unset
+    pub fn sound_timer_hz(mut self, hz: f64) -> Self {
+        self.sound_hz = Some(hz);
+        self
+    }
+
+    // loads everything but waits to be resumed before the first instruction
+    pub fn paused(mut self) -> Self {
+        self.paused = true;
+        self
+    }
+
+    // fails if the rom doesn't fit between the start address and the end of
+    // memory
+    pub fn build(self) -> Result<(Chip8, Handles), String> {
+        let mut chip8 = Chip8::new(self.config);
+        chip8.program_start = self.start;
+        chip8.pc = self.start;
+
+        let rom = self
+            .playlist
+            .as_ref()
+            .map(Playlist::current)
+            .or(self.rom.as_ref());
+        if let Some(rom) = rom {
+            chip8.load_rom(rom)?;
+            let mut shared = chip8.shared.lock().unwrap();
+            shared.rom_name = rom.name.clone();
+            shared.controls = rom.controls.clone();
+        }
+        {
+            let mut shared = chip8.shared.lock().unwrap();
+            if let Some(ips) = self.instrs_per_second {
+                shared.instrs_per_second = ips;
+            }
+            if let Some(ipf) = self.instrs_per_frame {
+                shared.set_instrs_per_frame(ipf);
+            }
+            shared.paused = self.paused;
+            shared.program_start = self.start;
+        }
+        if let Some(hz) = self.sound_hz {
+            chip8.timers.lock().unwrap().sound_hz = hz;
+        }
+        chip8.playlist = self.playlist;
+
+        let handles = Handles {
+            screen: Arc::clone(&chip8.screen),
+            timers: Arc::clone(&chip8.timers),
+            shared: Arc::clone(&chip8.shared),
+            keys: Arc::clone(&chip8.keys),
+        };
+        Ok((chip8, handles))
+    }
+}
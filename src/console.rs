@@ -0,0 +1,102 @@
+use macroquad::prelude::*;
+
+// how long the result of a command stays up after the console closes
+const MESSAGE_SECS: f64 = 3.0;
+
+// a one-line text box drawn over the bottom of the display
+#[derive(Debug, Clone, Default)]
+pub struct Console {
+    pub open: bool,
+    input: String,
+    // roms up and down cycle the input through, when opened as a picker
+    choices: Vec<String>,
+    choice: usize,
+    // result of the last command and when it came in
+    message: Option<(Result<String, String>, f64)>,
+}
+
+impl Console {
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+        self.input.clear();
+        self.choices.clear();
+        // drop whatever was typed to open it
+        while get_char_pressed().is_some() {}
+    }
+
+    // opens with the first of `choices` typed in, ready to run with enter
+    pub fn pick_from(&mut self, choices: Vec<String>) {
+        self.toggle();
+        self.input = choices.first().cloned().unwrap_or_default();
+        self.choices = choices;
+        self.choice = 0;
+    }
+
+    // successful commands close the console, failed ones leave it open to fix
+    pub fn report(&mut self, result: Result<String, String>) {
+        self.open = result.is_err();
+        self.message = Some((result, get_time()));
+    }
+
+    // reads this frame's typing, returns a line once enter is pressed
+    pub fn update(&mut self) -> Option<String> {
+        while let Some(ch) = get_char_pressed() {
+            if !ch.is_control() {
+                self.input.push(ch);
+            }
+        }
+        if !self.choices.is_empty() {
+            let step = match (is_key_pressed(KeyCode::Down), is_key_pressed(KeyCode::Up)) {
+                (true, false) => 1,
+                (false, true) => self.choices.len() - 1,
+                _ => 0,
+            };
+            if step != 0 {
+                self.choice = (self.choice + step) % self.choices.len();
+                self.input = self.choices[self.choice].clone();
+            }
+        }
+        if is_key_pressed(KeyCode::Backspace) {
+            self.input.pop();
+        }
+        if is_key_pressed(KeyCode::Escape) {
+            self.open = false;
+        }
+        if is_key_pressed(KeyCode::Enter) {
+            return Some(self.input.trim().to_string());
+        }
+        None
+    }
+
+    pub fn draw(&self, width: f32) {
+        let message = self
+            .message
+            .as_ref()
+            .filter(|(_, time)| self.open || get_time() - time < MESSAGE_SECS);
+        if !self.open && message.is_none() {
+            return;
+        }
+
+        let y = screen_height() - 50.0;
+        draw_rectangle(0.0, y, width, 50.0, Color::new(0.0, 0.0, 0.0, 0.8));
+        if self.open {
+            draw_text(&format!("> {}_", self.input), 5.0, y + 20.0, 20.0, WHITE);
+            if !self.choices.is_empty() {
+                let hint = format!(
+                    "rom {} of {}, up/down for others",
+                    self.choice + 1,
+                    self.choices.len()
+                );
+                let size = measure_text(&hint, None, 20, 1.0);
+                draw_text(&hint, width - size.width - 5.0, y + 20.0, 20.0, GRAY);
+            }
+        }
+        if let Some((result, _)) = message {
+            let (text, color) = match result {
+                Ok(text) => (text, GREEN),
+                Err(text) => (text, RED),
+            };
+            draw_text(text, 5.0, y + 42.0, 20.0, color);
+        }
+    }
+}
@@ -0,0 +1,172 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Write,
+};
+
+use crate::{instr::Instr, opcode::Opcode};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Byte {
+    Data,
+    // first byte of a reachable instruction
+    Code,
+    // second byte of a reachable instruction
+    Operand,
+}
+
+// which bytes of a rom are reachable code, found by following every path from
+// the entry point instead of sweeping linearly
+#[derive(Debug, Clone)]
+struct Flow<'a> {
+    rom: &'a [u8],
+    origin: usize,
+    bytes: Vec<Byte>,
+    jump_targets: BTreeSet<usize>,
+    call_targets: BTreeSet<usize>,
+    // BNNN depends on a register, so these are as far as the analysis goes
+    indirect_jumps: BTreeSet<usize>,
+}
+
+impl<'a> Flow<'a> {
+    fn trace(rom: &'a [u8], origin: usize, entry: usize) -> Self {
+        let mut flow = Self {
+            rom,
+            origin,
+            bytes: vec![Byte::Data; rom.len()],
+            jump_targets: BTreeSet::new(),
+            call_targets: BTreeSet::new(),
+            indirect_jumps: BTreeSet::new(),
+        };
+        let end = origin + rom.len();
+
+        let mut work = vec![entry];
+        while let Some(addr) = work.pop() {
+            if addr < origin || addr + 1 >= end || flow.bytes[addr - origin] == Byte::Code {
+                continue;
+            }
+            let offset = addr - origin;
+            flow.bytes[offset] = Byte::Code;
+            flow.bytes[offset + 1] = Byte::Operand;
+
+            let instr = Instr::new(rom[offset], rom[offset + 1]);
+            let next = addr + 2;
+            match Opcode::decode(&instr) {
+                // unknown opcodes would crash the interpreter, so this path ends
+                None => {}
+                // the caller's fallthrough was already queued
+                Some(Opcode::Return) => {}
+                // machine code for the host computer, nothing to follow
+                Some(Opcode::Sys(_)) => {}
+                Some(Opcode::Jump(address)) => {
+                    flow.jump_targets.insert(address as usize);
+                    work.push(address as usize);
+                }
+                Some(Opcode::Call(address)) => {
+                    flow.call_targets.insert(address as usize);
+                    work.push(address as usize);
+                    work.push(next);
+                }
+                Some(
+                    Opcode::SkipEqImmediate { .. }
+                    | Opcode::SkipNeImmediate { .. }
+                    | Opcode::SkipEqRegister { .. }
+                    | Opcode::SkipNeRegister { .. }
+                    | Opcode::SkipPressed { .. }
+                    | Opcode::SkipNotPressed { .. },
+                ) => {
+                    work.push(next);
+                    work.push(next + 2);
+                }
+                Some(Opcode::JumpWithOffset { .. }) => {
+                    flow.indirect_jumps.insert(addr);
+                }
+                _ => work.push(next),
+            }
+        }
+        flow
+    }
+
+    // reachable instructions in address order
+    fn instrs(&self) -> impl Iterator<Item = (usize, Instr)> + '_ {
+        self.bytes
+            .iter()
+            .enumerate()
+            .filter(|(_, byte)| **byte == Byte::Code)
+            .map(|(offset, _)| {
+                let instr = Instr::new(self.rom[offset], self.rom[offset + 1]);
+                (self.origin + offset, instr)
+            })
+    }
+}
+
+// Disassembles `rom` (loaded at `origin`) along its control flow from `entry`.
+// Anything never reached is printed as data, and jump/call targets get labels.
+pub fn disassemble_flow(rom: &[u8], origin: usize, entry: usize) -> String {
+    let flow = Flow::trace(rom, origin, entry);
+
+    let mut out = String::new();
+    for (offset, byte) in flow.bytes.iter().enumerate() {
+        let addr = origin + offset;
+        match byte {
+            Byte::Code => {
+                if flow.call_targets.contains(&addr) {
+                    writeln!(out, "sub_{addr:03X}:").unwrap();
+                } else if flow.jump_targets.contains(&addr) {
+                    writeln!(out, "label_{addr:03X}:").unwrap();
+                }
+                let instr = Instr::new(rom[offset], rom[offset + 1]);
+                write!(out, "    {addr:#05X}  {}", instr.disassemble()).unwrap();
+                if flow.indirect_jumps.contains(&addr) {
+                    write!(out, "  ; indirect jump, not followed").unwrap();
+                }
+                writeln!(out).unwrap();
+            }
+            Byte::Operand => {}
+            Byte::Data => writeln!(out, "    {addr:#05X}  DB {:#04X}", rom[offset]).unwrap(),
+        }
+    }
+    out
+}
+
+// opcodes from the extensions, most of which this interpreter doesn't run
+fn extension(instr: &Instr) -> Option<&'static str> {
+    match instr.as_nibbles() {
+        (0x0, 0x0, 0xC, _)
+        | (0x0, 0x0, 0xF, 0xB..=0xF)
+        | (0xD, _, _, 0x0)
+        | (0xF, _, 0x3, 0x0)
+        | (0xF, _, 0x7, 0x5)
+        | (0xF, _, 0x8, 0x5) => Some("SUPER-CHIP"),
+        (0x5, _, _, 0x2 | 0x3)
+        | (0xF, 0x0, 0x0, 0x0)
+        | (0xF, _, 0x0, 0x1)
+        | (0xF, 0x0, 0x0, 0x2) => Some("XO-CHIP"),
+        _ => None,
+    }
+}
+
+// how many of each opcode the reachable code contains
+pub fn opcode_histogram(rom: &[u8], origin: usize, entry: usize) -> String {
+    let flow = Flow::trace(rom, origin, entry);
+
+    let mut counts = BTreeMap::new();
+    let mut extensions = BTreeSet::new();
+    let mut total = 0;
+    for (_, instr) in flow.instrs() {
+        total += 1;
+        *counts
+            .entry(instr.pattern().unwrap_or("unknown"))
+            .or_insert(0) += 1;
+        extensions.extend(extension(&instr));
+    }
+
+    let mut out = String::new();
+    writeln!(out, "instructions: {total}").unwrap();
+    for (pattern, count) in counts {
+        writeln!(out, "    {pattern:<8} {count}").unwrap();
+    }
+    for ext in extensions {
+        writeln!(out, "uses {ext} opcodes").unwrap();
+    }
+    out
+}
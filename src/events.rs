@@ -0,0 +1,51 @@
+use std::fmt;
+
+// things the interpreter does that something outside it might want to react
+// to, sent to whoever subscribed with Chip8::subscribe
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event {
+    Draw {
+        x: u8,
+        y: u8,
+        rows: u8,
+        collision: bool,
+    },
+    // FX18 with a non-zero value, in sound timer ticks
+    SoundStart {
+        ticks: u8,
+    },
+    // an instruction lenient mode skipped instead of panicking on
+    Fault {
+        pc: usize,
+        instr: [u8; 2],
+    },
+    SubroutineCall {
+        from: usize,
+        to: usize,
+    },
+    // anything that sets VF as a flag, the same writes --log-vf prints
+    FlagWrite {
+        pc: usize,
+        value: u8,
+    },
+}
+
+impl fmt::Display for Event {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Event::Draw {
+                x,
+                y,
+                rows,
+                collision,
+            } => write!(f, "draw {rows} rows at ({x}, {y}), collision: {collision}"),
+            Event::SoundStart { ticks } => write!(f, "sound for {ticks} ticks"),
+            Event::Fault {
+                pc,
+                instr: [b1, b2],
+            } => write!(f, "fault at {pc:#05X} on {b1:02X}{b2:02X}"),
+            Event::SubroutineCall { from, to } => write!(f, "call {to:#05X} from {from:#05X}"),
+            Event::FlagWrite { pc, value } => write!(f, "VF = {value:#04X} at {pc:#05X}"),
+        }
+    }
+}
@@ -0,0 +1,66 @@
+use std::{
+    any::Any,
+    fmt::Write,
+    fs,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{Chip8, instr::Instr, memmap::memory_map};
+
+// what a panic was raised with, for the ones raised by panic! with a message
+pub fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("unknown panic")
+}
+
+// writes a report on a fault to a new file in `dir`, and the machine's state
+// next to it so the fault can be reproduced. `message` says what went wrong
+pub fn report(chip8: &Chip8, dir: &Path, message: &str) {
+    match write_report(chip8, dir, message) {
+        Ok((report, state)) => eprintln!(
+            "wrote a fault report to {}, run with --state {} and press End to go back to it",
+            report.display(),
+            state.display()
+        ),
+        Err(e) => eprintln!("{e}"),
+    }
+}
+
+// everything needed to tell what the rom was doing when it faulted: the
+// message, registers, the instructions leading up to it and all of memory.
+// returns the report's file and the snapshot's
+fn write_report(chip8: &Chip8, dir: &Path, message: &str) -> Result<(PathBuf, PathBuf), String> {
+    let mut out = String::new();
+    writeln!(out, "fault: {message}").unwrap();
+    writeln!(out, "rom: {}", chip8.shared.lock().unwrap().rom_name).unwrap();
+    writeln!(out, "after {} instructions\n", chip8.instrs_executed).unwrap();
+
+    writeln!(out, "pc = {:#05X}, I = {:#05X}", chip8.pc, chip8.i).unwrap();
+    let registers = (0..0x10).map(|x| format!("V{x:X} = {:#04X}", chip8.registers.get(x)));
+    writeln!(out, "{}", registers.collect::<Vec<_>>().join("  ")).unwrap();
+    let stack = chip8.stack.iter().map(|ret| format!("{ret:#05X}"));
+    writeln!(out, "stack: {}\n", stack.collect::<Vec<_>>().join(" ")).unwrap();
+
+    writeln!(out, "last instructions:").unwrap();
+    for &(pc, [b1, b2]) in &chip8.recent_instrs {
+        writeln!(out, "    {pc:#05X}  {}", Instr::new(b1, b2)).unwrap();
+    }
+    writeln!(out, "\nmemory:").unwrap();
+    out.push_str(&memory_map(chip8, chip8.pc));
+
+    fs::create_dir_all(dir).map_err(|e| format!("could not create {}: {e}", dir.display()))?;
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    // the instruction count keeps two faults in the same second apart
+    let name = format!("fault-{secs}-{}", chip8.instrs_executed);
+    let path = dir.join(format!("{name}.txt"));
+    fs::write(&path, out).map_err(|e| format!("could not write {}: {e}", path.display()))?;
+    let state = dir.join(format!("{name}.toml"));
+    chip8.snapshot().save(&state)?;
+    Ok((path, state))
+}
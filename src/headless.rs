@@ -0,0 +1,170 @@
+use std::path::Path;
+
+use macroquad::color::Color;
+
+use crate::{Chip8, builder::Chip8Builder, playlist::Rom, screen::Screen, tern};
+
+// Timendus' opcode test (corax+), reused for the selftest instead of bundling a
+// rom of our own. its readme documents a passing run as a check mark next to
+// every opcode label, so that's what the selftest looks for
+const SELFTEST_ROM: &[u8] = include_bytes!("../programs/tests/3-corax+.ch8");
+const SELFTEST_FRAMES: u32 = 60;
+// corax+ draws each result with one of these 4 row sprites out of the rom
+const SELFTEST_CROSS: usize = 0x4A1;
+const SELFTEST_CHECK: usize = 0x4A5;
+
+// size of a chip-8 pixel in captured images
+const CAPTURE_SCALE: u32 = 10;
+
+// runs `frames` frames with no window, then writes the screen out as a png
+pub fn capture(
+    chip8: &mut Chip8,
+    frames: u32,
+    out: &Path,
+    palette: &[Color; 4],
+) -> Result<(), String> {
+    chip8.run_frames(frames);
+    let screen = chip8.screen.lock().unwrap();
+    write_png(&screen, out, palette)
+}
+
+// runs up to `max_instrs` with no window, then reports where it stopped and
+// what's on the screen
+pub fn run(chip8: &mut Chip8, pc: usize, max_instrs: usize) -> String {
+    let ran = chip8.run_headless(pc, max_instrs);
+    let registers = (0..0x10)
+        .map(|x| format!("V{x:X}={:02X}", chip8.registers.get(x)))
+        .collect::<Vec<_>>();
+    format!(
+        "ran {ran} instructions, pc = {:#05X}, I = {:#05X}\n{}\n{}\n",
+        chip8.pc,
+        chip8.i,
+        registers.join(" "),
+        chip8.screen.lock().unwrap().to_ascii()
+    )
+}
+
+pub fn write_png(screen: &Screen, out: &Path, palette: &[Color; 4]) -> Result<(), String> {
+    let (width, height) = (64 * CAPTURE_SCALE, 32 * CAPTURE_SCALE);
+    let mut pixels = Vec::with_capacity((width * height * 4) as usize);
+    for y in 0..height {
+        for x in 0..width {
+            let value = screen.pixel((x / CAPTURE_SCALE) as usize, (y / CAPTURE_SCALE) as usize);
+            let rgba: [u8; 4] = palette[value as usize].into();
+            pixels.extend(rgba);
+        }
+    }
+    image::save_buffer(out, &pixels, width, height, image::ColorType::Rgba8)
+        .map_err(|e| format!("could not write {}: {e}", out.display()))
+}
+
+// runs the bundled opcode test with the default config and checks that every
+// result it draws is a check mark
+pub fn selftest() -> bool {
+    let (mut chip8, _) = Chip8Builder::new()
+        .rom(Rom {
+            name: "3-corax+.ch8".into(),
+            bytes: SELFTEST_ROM.to_vec(),
+            controls: vec![],
+        })
+        .build()
+        .expect("the selftest rom fits in memory");
+    chip8.run_frames(SELFTEST_FRAMES);
+
+    let screen = chip8.screen.lock().unwrap();
+    let cross = sprite(SELFTEST_ROM, SELFTEST_CROSS, 4);
+    let check = sprite(SELFTEST_ROM, SELFTEST_CHECK, 4);
+    let failed = selftest_results()
+        .filter(|&(x, y)| !shows(&screen, x, y, check))
+        .collect::<Vec<_>>();
+    if failed.is_empty() {
+        println!("PASS");
+        true
+    } else {
+        for (x, y) in failed {
+            let seen = tern!(shows(&screen, x, y, cross), "a cross", "no mark");
+            println!("FAIL: {seen} instead of a check at ({x}, {y})");
+        }
+        println!("{}", screen.to_ascii());
+        false
+    }
+}
+
+// where corax+ draws its 22 results: 9 pixels right of each label in a 4
+// column grid, with the bottom two rows only filling 3 columns
+fn selftest_results() -> impl Iterator<Item = (usize, usize)> {
+    (1..=26).step_by(5).enumerate().flat_map(|(row, y)| {
+        let columns = tern!(row < 4, 4, 3);
+        [11, 27, 43, 59]
+            .into_iter()
+            .take(columns)
+            .map(move |x| (x, y))
+    })
+}
+
+// a sprite as the rom has it at `addr`
+fn sprite(rom: &[u8], addr: usize, rows: usize) -> &[u8] {
+    &rom[addr - 0x200..addr - 0x200 + rows]
+}
+
+// whether the 4 pixel wide block at (x, y) is exactly `sprite`
+fn shows(screen: &Screen, x: usize, y: usize, sprite: &[u8]) -> bool {
+    sprite.iter().enumerate().all(|(row, byte)| {
+        (0..4).all(|col| screen.pixel(x + col, y + row) == (byte >> (7 - col)) & 1)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FLAGS_ROM: &[u8] = include_bytes!("../programs/tests/4-flags.ch8");
+
+    fn boot(name: &str, bytes: &[u8]) -> Chip8 {
+        let (chip8, _) = Chip8Builder::new()
+            .rom(Rom {
+                name: name.into(),
+                bytes: bytes.to_vec(),
+                controls: vec![],
+            })
+            .build()
+            .unwrap();
+        chip8
+    }
+
+    fn count(screen: &Screen, sprite: &[u8]) -> usize {
+        (0..=64 - 4)
+            .flat_map(|x| (0..=32 - sprite.len()).map(move |y| (x, y)))
+            .filter(|&(x, y)| shows(screen, x, y, sprite))
+            .count()
+    }
+
+    #[test]
+    fn corax_shows_a_check_for_every_opcode() {
+        let mut chip8 = boot("3-corax+.ch8", SELFTEST_ROM);
+        chip8.run_frames(SELFTEST_FRAMES);
+
+        let cross = sprite(SELFTEST_ROM, SELFTEST_CROSS, 4);
+        let check = sprite(SELFTEST_ROM, SELFTEST_CHECK, 4);
+        let screen = chip8.screen.lock().unwrap();
+        assert_eq!(selftest_results().count(), 22);
+        for (x, y) in selftest_results() {
+            assert!(!shows(&screen, x, y, cross), "cross at ({x}, {y})");
+            assert!(shows(&screen, x, y, check), "no check at ({x}, {y})");
+        }
+    }
+
+    #[test]
+    fn flags_test_halts_without_a_cross() {
+        let mut chip8 = boot("4-flags.ch8", FLAGS_ROM);
+        chip8.run_headless(0x200, 10_000);
+
+        // the flags test ends on a jump to itself once every row is drawn
+        assert_eq!(chip8.pc, 0x542);
+        let cross = sprite(FLAGS_ROM, 0x558, 3);
+        let check = sprite(FLAGS_ROM, 0x555, 3);
+        let screen = chip8.screen.lock().unwrap();
+        assert_eq!(count(&screen, cross), 0);
+        assert!(count(&screen, check) > 0);
+    }
+}
@@ -0,0 +1,271 @@
+use std::{collections::HashMap, fs, path::Path};
+
+use macroquad::input::KeyCode;
+
+// keys that control the emulator itself rather than the chip-8 keypad
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hotkeys {
+    pub fast_forward: KeyCode,
+    pub speed_up: KeyCode,
+    pub speed_down: KeyCode,
+    pub slow_motion: KeyCode,
+    pub normal_motion: KeyCode,
+    pub pause: KeyCode,
+    pub pause_timers: KeyCode,
+    pub playlist_next: KeyCode,
+    pub playlist_prev: KeyCode,
+    pub copy_screen: KeyCode,
+    pub hud: KeyCode,
+    pub coverage: KeyCode,
+    pub ghosting: KeyCode,
+    pub theme: KeyCode,
+    pub console: KeyCode,
+    pub step: KeyCode,
+    pub step_to_draw: KeyCode,
+    pub step_to_key_read: KeyCode,
+    pub hex_input: KeyCode,
+    pub volume_up: KeyCode,
+    pub volume_down: KeyCode,
+    pub save_snapshot: KeyCode,
+    pub load_snapshot: KeyCode,
+    pub rewind: KeyCode,
+    pub reset: KeyCode,
+    pub quirk_bitshift_copies_y: KeyCode,
+    pub quirk_shift_vf_from_vx: KeyCode,
+    pub quirk_jump_with_offset_register: KeyCode,
+    pub quirk_update_i_after_store_or_load: KeyCode,
+}
+
+impl Default for Hotkeys {
+    fn default() -> Self {
+        Self {
+            fast_forward: KeyCode::Tab,
+            speed_up: KeyCode::Up,
+            speed_down: KeyCode::Down,
+            slow_motion: KeyCode::LeftBracket,
+            normal_motion: KeyCode::RightBracket,
+            pause: KeyCode::P,
+            pause_timers: KeyCode::T,
+            playlist_next: KeyCode::PageDown,
+            playlist_prev: KeyCode::PageUp,
+            copy_screen: KeyCode::F2,
+            hud: KeyCode::F3,
+            coverage: KeyCode::F5,
+            ghosting: KeyCode::F4,
+            theme: KeyCode::Insert,
+            console: KeyCode::Enter,
+            step: KeyCode::Space,
+            step_to_draw: KeyCode::F6,
+            step_to_key_read: KeyCode::F7,
+            hex_input: KeyCode::F8,
+            volume_up: KeyCode::Equal,
+            volume_down: KeyCode::Minus,
+            save_snapshot: KeyCode::Home,
+            load_snapshot: KeyCode::End,
+            rewind: KeyCode::Backspace,
+            reset: KeyCode::F1,
+            quirk_bitshift_copies_y: KeyCode::F9,
+            quirk_shift_vf_from_vx: KeyCode::F10,
+            quirk_jump_with_offset_register: KeyCode::F11,
+            quirk_update_i_after_store_or_load: KeyCode::F12,
+        }
+    }
+}
+
+impl Hotkeys {
+    // reads a toml file of `action = "Key"` pairs, anything left out keeps
+    // its default
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = fs::read_to_string(path)
+            .map_err(|e| format!("could not read {}: {e}", path.display()))?;
+        let table: HashMap<String, String> = toml::from_str(&text)
+            .map_err(|e| format!("could not parse {}: {e}", path.display()))?;
+
+        let mut hotkeys = Self::default();
+        for (action, name) in table {
+            let key = key_from_name(&name).ok_or(format!("unknown key {name:?} for {action}"))?;
+            let slot = match action.as_str() {
+                "fast_forward" => &mut hotkeys.fast_forward,
+                "speed_up" => &mut hotkeys.speed_up,
+                "speed_down" => &mut hotkeys.speed_down,
+                "slow_motion" => &mut hotkeys.slow_motion,
+                "normal_motion" => &mut hotkeys.normal_motion,
+                "pause" => &mut hotkeys.pause,
+                "pause_timers" => &mut hotkeys.pause_timers,
+                "playlist_next" => &mut hotkeys.playlist_next,
+                "playlist_prev" => &mut hotkeys.playlist_prev,
+                "copy_screen" => &mut hotkeys.copy_screen,
+                "hud" => &mut hotkeys.hud,
+                "coverage" => &mut hotkeys.coverage,
+                "ghosting" => &mut hotkeys.ghosting,
+                "theme" => &mut hotkeys.theme,
+                "console" => &mut hotkeys.console,
+                "step" => &mut hotkeys.step,
+                "step_to_draw" => &mut hotkeys.step_to_draw,
+                "step_to_key_read" => &mut hotkeys.step_to_key_read,
+                "hex_input" => &mut hotkeys.hex_input,
+                "volume_up" => &mut hotkeys.volume_up,
+                "volume_down" => &mut hotkeys.volume_down,
+                "save_snapshot" => &mut hotkeys.save_snapshot,
+                "load_snapshot" => &mut hotkeys.load_snapshot,
+                "rewind" => &mut hotkeys.rewind,
+                "reset" => &mut hotkeys.reset,
+                "quirk_bitshift_copies_y" => &mut hotkeys.quirk_bitshift_copies_y,
+                "quirk_shift_vf_from_vx" => &mut hotkeys.quirk_shift_vf_from_vx,
+                "quirk_jump_with_offset_register" => &mut hotkeys.quirk_jump_with_offset_register,
+                "quirk_update_i_after_store_or_load" => {
+                    &mut hotkeys.quirk_update_i_after_store_or_load
+                }
+                _ => return Err(format!("unknown hotkey action {action:?}")),
+            };
+            *slot = key;
+        }
+        Ok(hotkeys)
+    }
+}
+
+// reads a toml file of `0 = "X"` pairs giving the keyboard key for each
+// chip-8 key. unlike the hotkeys there's no default to fall back on, so all
+// 16 have to be there
+pub fn load_key_map(path: &Path) -> Result<[KeyCode; 0x10], String> {
+    let text =
+        fs::read_to_string(path).map_err(|e| format!("could not read {}: {e}", path.display()))?;
+    let table: HashMap<String, String> =
+        toml::from_str(&text).map_err(|e| format!("could not parse {}: {e}", path.display()))?;
+
+    let mut map = [None; 0x10];
+    for (key, name) in table {
+        let index = u8::from_str_radix(&key, 16)
+            .ok()
+            .filter(|k| *k < 0x10)
+            .ok_or(format!("{key:?} is not a chip-8 key from 0 to F"))?;
+        let code = key_from_name(&name).ok_or(format!("unknown key {name:?} for {key}"))?;
+        if map[index as usize].is_some() {
+            return Err(format!("chip-8 key {index:X} is mapped twice"));
+        }
+        if let Some(other) = map.iter().position(|k| *k == Some(code)) {
+            return Err(format!("{name} is mapped to both {other:X} and {index:X}"));
+        }
+        map[index as usize] = Some(code);
+    }
+    let missing = (0..0x10)
+        .filter(|k| map[*k].is_none())
+        .map(|k| format!("{k:X}"))
+        .collect::<Vec<_>>();
+    if !missing.is_empty() {
+        return Err(format!(
+            "{} has no mapping for {}",
+            path.display(),
+            missing.join(" ")
+        ));
+    }
+    Ok(map.map(Option::unwrap))
+}
+
+// every key that can be bound, named the same as the KeyCode variant
+const KEY_NAMES: &[KeyCode] = &[
+    KeyCode::Space,
+    KeyCode::Apostrophe,
+    KeyCode::Comma,
+    KeyCode::Minus,
+    KeyCode::Period,
+    KeyCode::Slash,
+    KeyCode::Key0,
+    KeyCode::Key1,
+    KeyCode::Key2,
+    KeyCode::Key3,
+    KeyCode::Key4,
+    KeyCode::Key5,
+    KeyCode::Key6,
+    KeyCode::Key7,
+    KeyCode::Key8,
+    KeyCode::Key9,
+    KeyCode::Semicolon,
+    KeyCode::Equal,
+    KeyCode::A,
+    KeyCode::B,
+    KeyCode::C,
+    KeyCode::D,
+    KeyCode::E,
+    KeyCode::F,
+    KeyCode::G,
+    KeyCode::H,
+    KeyCode::I,
+    KeyCode::J,
+    KeyCode::K,
+    KeyCode::L,
+    KeyCode::M,
+    KeyCode::N,
+    KeyCode::O,
+    KeyCode::P,
+    KeyCode::Q,
+    KeyCode::R,
+    KeyCode::S,
+    KeyCode::T,
+    KeyCode::U,
+    KeyCode::V,
+    KeyCode::W,
+    KeyCode::X,
+    KeyCode::Y,
+    KeyCode::Z,
+    KeyCode::LeftBracket,
+    KeyCode::Backslash,
+    KeyCode::RightBracket,
+    KeyCode::GraveAccent,
+    KeyCode::Escape,
+    KeyCode::Enter,
+    KeyCode::Tab,
+    KeyCode::Backspace,
+    KeyCode::Insert,
+    KeyCode::Delete,
+    KeyCode::Right,
+    KeyCode::Left,
+    KeyCode::Down,
+    KeyCode::Up,
+    KeyCode::PageUp,
+    KeyCode::PageDown,
+    KeyCode::Home,
+    KeyCode::End,
+    KeyCode::F1,
+    KeyCode::F2,
+    KeyCode::F3,
+    KeyCode::F4,
+    KeyCode::F5,
+    KeyCode::F6,
+    KeyCode::F7,
+    KeyCode::F8,
+    KeyCode::F9,
+    KeyCode::F10,
+    KeyCode::F11,
+    KeyCode::F12,
+    KeyCode::Kp0,
+    KeyCode::Kp1,
+    KeyCode::Kp2,
+    KeyCode::Kp3,
+    KeyCode::Kp4,
+    KeyCode::Kp5,
+    KeyCode::Kp6,
+    KeyCode::Kp7,
+    KeyCode::Kp8,
+    KeyCode::Kp9,
+    KeyCode::KpDecimal,
+    KeyCode::KpDivide,
+    KeyCode::KpMultiply,
+    KeyCode::KpSubtract,
+    KeyCode::KpAdd,
+    KeyCode::KpEnter,
+    KeyCode::KpEqual,
+    KeyCode::LeftShift,
+    KeyCode::LeftControl,
+    KeyCode::LeftAlt,
+    KeyCode::RightShift,
+    KeyCode::RightControl,
+    KeyCode::RightAlt,
+];
+
+pub fn key_from_name(name: &str) -> Option<KeyCode> {
+    KEY_NAMES
+        .iter()
+        .copied()
+        .find(|key| format!("{key:?}").eq_ignore_ascii_case(name))
+}
@@ -0,0 +1,61 @@
+use std::fmt;
+
+use crate::{Config, opcode::Opcode};
+
+#[derive(Clone, Copy)]
+pub struct Instr {
+    b1: u8,
+    b2: u8,
+}
+
+impl Instr {
+    pub fn new(b1: u8, b2: u8) -> Self {
+        Self { b1, b2 }
+    }
+    pub fn as_nibbles(&self) -> (u8, u8, u8, u8) {
+        (
+            ((self.b1 & 0xf0) >> 4),
+            (self.b1 & 0x0f),
+            ((self.b2 & 0xf0) >> 4),
+            (self.b2 & 0x0f),
+        )
+    }
+    pub fn as_bytes(&self) -> [u8; 2] {
+        [self.b1, self.b2]
+    }
+    pub fn as_u8(&self) -> u8 {
+        self.b2
+    }
+    pub fn as_address(&self) -> usize {
+        let a = (self.b1 & 0x0F) as usize;
+        a << 8 | (self.b2 as usize)
+    }
+
+    // the opcode's spec-style name like "8XY4", None if it isn't one
+    pub fn pattern(&self) -> Option<&'static str> {
+        Opcode::decode(self).map(|op| Opcode::PATTERNS[op.index()])
+    }
+
+    // cowgod-style mnemonic, anything that isn't a known opcode is shown as a
+    // data word since roms mix code and sprites
+    pub fn disassemble(&self) -> String {
+        match Opcode::decode(self) {
+            Some(op) => op.to_string(),
+            None => format!("DB {self}"),
+        }
+    }
+
+    // disassemble, but as `config`'s quirks would run it
+    pub fn disassemble_with(&self, config: &Config) -> String {
+        match Opcode::decode(self) {
+            Some(op) => op.mnemonic(config),
+            None => format!("DB {self}"),
+        }
+    }
+}
+
+impl fmt::Display for Instr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "0x{:02X}{:02X}", self.b1, self.b2)
+    }
+}
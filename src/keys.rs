@@ -1,41 +1,73 @@
-use std::{collections::HashSet, slice};
-
-use macroquad::input::KeyCode;
-
 #[derive(Debug, Default, Clone)]
 pub struct Keys([bool; 0x10]);
 
-const KEY_MAP: [KeyCode; 0x10] = [
-    KeyCode::X,    // 0
-    KeyCode::Key1, // 1
-    KeyCode::Key2, // 2
-    KeyCode::Key3, // 3
-    KeyCode::Q,    // 4
-    KeyCode::W,    // 5
-    KeyCode::E,    // 6
-    KeyCode::A,    // 7
-    KeyCode::S,    // 8
-    KeyCode::D,    // 9
-    KeyCode::Z,    // A
-    KeyCode::C,    // B
-    KeyCode::Key4, // C
-    KeyCode::R,    // D
-    KeyCode::F,    // E
-    KeyCode::V,    // F
-];
-
 impl Keys {
+    // same as Registers::index, callers mask or check keys before they get
+    // here so anything past F is a bug
+    fn index(key: u8) -> usize {
+        debug_assert!(key < 0x10, "key {key:#X} is out of range");
+        key as usize
+    }
+
+    // `x` is 0x0..=0xF, Chip8::key_index masks VX down to that
     pub fn get(&self, x: u8) -> bool {
-        self.0[x as usize % 0xF]
+        self.0[Self::index(x)]
     }
 
-    pub fn iter(&self) -> slice::Iter<'_, bool> {
-        self.0.iter()
+    // marks a chip-8 key (0x0..=0xF) as held down
+    pub fn press(&mut self, key: u8) {
+        self.0[Self::index(key)] = true;
+    }
+
+    pub fn release(&mut self, key: u8) {
+        self.0[Self::index(key)] = false;
+    }
+
+    // bit n is chip-8 key n
+    pub fn from_u16(down: u16) -> Self {
+        let mut keys = Self::default();
+        keys.set(down);
+        keys
+    }
+
+    pub fn as_u16(&self) -> u16 {
+        self.0
+            .iter()
+            .enumerate()
+            .filter(|(_, down)| **down)
+            .fold(0, |bits, (key, _)| bits | 1 << key)
+    }
+
+    pub fn set(&mut self, down: u16) {
+        for key in 0..0x10 {
+            if down & (1 << key) != 0 {
+                self.press(key);
+            } else {
+                self.release(key);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn u16_round_trips() {
+        for down in [0x0000, 0x0001, 0x8000, 0x1234, 0xA5A5, 0xFFFF] {
+            assert_eq!(Keys::from_u16(down).as_u16(), down);
+        }
     }
 
-    pub fn set(&mut self, down: HashSet<KeyCode>) {
-        for (pressed, key) in self.0.iter_mut().zip(KEY_MAP.iter()) {
-            *pressed = down.contains(key);
+    #[test]
+    fn each_key_is_independent() {
+        for key in 0..0x10 {
+            let mut keys = Keys::default();
+            keys.press(key);
+            for other in 0..0x10 {
+                assert_eq!(keys.get(other), other == key, "key {key:X} pressed");
+            }
         }
     }
 }
@@ -1,19 +1,57 @@
 use std::{
-    fmt,
+    collections::{HashSet, VecDeque},
+    fs,
     hash::Hash,
-    sync::{Arc, Mutex},
+    io::{self, Write},
+    mem,
+    panic::{self, AssertUnwindSafe},
+    path::PathBuf,
+    process,
+    sync::{
+        Arc, Condvar, Mutex,
+        atomic::{self, AtomicU8},
+        mpsc::{self, Receiver, Sender},
+    },
     thread,
     time::{Duration, Instant},
 };
 
-use memory::Memory;
+use events::Event;
+use instr::Instr;
+use memory::{FONT_START, Memory, MemorySize};
+use opcode::Opcode;
+use playlist::{Playlist, Rom};
+use rand::{Rng, SeedableRng, rngs::StdRng};
+use rand_chacha::ChaCha12Rng;
 use registers::Registers;
-use screen::Screen;
-
+use screen::{PLANES, Screen};
+use serde::{Deserialize, Serialize};
+use snapshot::Snapshot;
+use timers::Timers;
+use timing::{InstrTimes, Pacer};
+
+mod args;
+mod audio;
+mod builder;
+mod console;
+mod disasm;
+mod events;
+mod fault;
+mod headless;
+mod hotkeys;
+mod instr;
 mod keys;
+mod memmap;
 mod memory;
+mod opcode;
+mod playlist;
+mod prefs;
 mod registers;
 mod screen;
+mod snapshot;
+mod sprite;
+mod timers;
+mod timing;
 mod window;
 
 #[macro_export]
@@ -23,57 +61,173 @@ macro_rules! tern {
     };
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Default, Hash)]
+const PROGRAM_START: usize = 0x200;
+
+// how often a paused compute thread checks whether it was resumed
+const PAUSE_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+// deepest the stack goes on the original interpreter, deeper in a debug
+// build is reported as a likely runaway recursion
+const STACK_LIMIT: usize = 16;
+
+// how often --watch checks the rom file for changes
+const WATCH_INTERVAL: Duration = Duration::from_millis(500);
+
+// instructions leading up to a fault that its report lists
+const RECENT_INSTRS: usize = 32;
+
+// longest delay timer polling loop fast_boot recognises
+const FAST_BOOT_LOOP_LEN: u64 = 8;
+// how much faster instructions and timers run while fast_boot is hurrying
+// through a splash screen
+const FAST_BOOT_SPEEDUP: f64 = 10.0;
+
+// how debug_print_instrs writes each instruction
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Hash, Serialize, Deserialize)]
+enum TraceFormat {
+    #[default]
+    Text,
+    // pc, opcode, mnemonic, V0..VF, I and stack depth before each instruction
+    Csv,
+}
+
+// which key FX0A takes when more than one is down
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Hash, Serialize, Deserialize)]
+enum WaitKeyPolicy {
+    #[default]
+    Lowest,
+    // whichever went down since FX0A last looked, the lowest of those if a
+    // few did at once and the lowest held key if none did
+    Newest,
+    // keep waiting until exactly one key is down
+    OnlyOne,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default, Hash, Serialize, Deserialize)]
 struct Config {
     bitshift_copies_y: bool,
+    shift_vf_from_vx: bool,
     jump_with_offset_register: bool,
     update_i_after_store_or_load: bool,
     debug_print_instrs: bool,
+    trace_format: TraceFormat,
+    debug_log_vf: bool,
+    debug_log_pc_range: bool,
+    debug_time_instrs: bool,
+    memory_size: MemorySize,
+    lenient_opcodes: bool,
+    strict_memory_bounds: bool,
+    strict_key_index: bool,
+    // fetching from an odd pc is an error, off since some roms misalign pc
+    // on purpose
+    strict_pc_alignment: bool,
+    wait_key_policy: WaitKeyPolicy,
+    sprite_wrapping: bool,
+    // DXYN waits for the next 60Hz frame before drawing, like the COSMAC VIP
+    display_wait: bool,
+    // how many frames back the rewind hotkey can go, 0 to keep none
+    rewind_frames: usize,
+    // speed up while a rom is waiting on the delay timer before it's read any
+    // input, see Chip8::detect_splash_wait
+    fast_boot: bool,
+    // fill registers and memory with noise from this seed at power on, to
+    // catch roms that read memory they never wrote
+    random_init_seed: Option<u64>,
+    // what CXNN draws from, the same numbers every run when set and fresh
+    // ones from the os otherwise
+    rng_seed: Option<u64>,
 }
 
-struct Instr {
-    b1: u8,
-    b2: u8,
+// what a paused machine runs up to before pausing again
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RunUntil {
+    // just the next instruction
+    Step,
+    Draw,
+    KeyRead,
+    // the current subroutine returning, which the compute thread turns into
+    // a ReturnTo with the address on top of the stack
+    Return,
+    // pc reaching `address` with fewer than `depth` return addresses left,
+    // so recursive calls back through the same address don't count
+    ReturnTo { address: usize, depth: usize },
 }
 
-impl Instr {
-    fn new(b1: u8, b2: u8) -> Self {
-        Self { b1, b2 }
-    }
-    fn as_nibbles(&self) -> (u8, u8, u8, u8) {
-        (
-            ((self.b1 & 0xf0) >> 4),
-            (self.b1 & 0x0f),
-            ((self.b2 & 0xf0) >> 4),
-            (self.b2 & 0x0f),
-        )
-    }
-    fn as_u8(&self) -> u8 {
-        self.b2
-    }
-    fn as_address(&self) -> usize {
-        let a = (self.b1 & 0x0F) as usize;
-        a << 8 | (self.b2 as usize)
+impl RunUntil {
+    // see Chip8::reached for the ones that depend on more than the instruction
+    fn matches(self, instr: &Instr) -> bool {
+        match self {
+            RunUntil::Step => true,
+            RunUntil::Draw => matches!(Opcode::decode(instr), Some(Opcode::Display { .. })),
+            RunUntil::KeyRead => matches!(
+                Opcode::decode(instr),
+                Some(
+                    Opcode::SkipPressed { .. }
+                        | Opcode::SkipNotPressed { .. }
+                        | Opcode::WaitKey { .. }
+                )
+            ),
+            RunUntil::Return | RunUntil::ReturnTo { .. } => false,
+        }
     }
 }
 
-impl fmt::Display for Instr {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "0x{:02X}{:02X}", self.b1, self.b2)
-    }
+// requests typed into the console while paused, or from debug hotkeys
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Edit {
+    Warp(usize),
+    SetRegister(u8, u8),
+    Poke(usize, u8),
+    ToggleQuirk(Quirk),
+    // print the sprite at I with this many rows
+    DumpSprite(u8),
+    // print this many bytes from an address as instructions
+    Disassemble(usize, usize),
+    // run one instruction that isn't in memory
+    Execute(u8, u8),
+    // the quick slot, from the hotkeys
+    SaveSnapshot,
+    LoadSnapshot,
+    // print what's changed since the quick slot was saved
+    DiffSnapshot,
+    // back to the newest snapshot in the rewind buffer
+    Rewind,
+    // start the loaded rom over
+    Reset,
 }
 
-#[derive(Debug, Clone)]
-struct Timers {
-    delay_timer: u8,
-    sound_timer: u8,
+// the Config flags that differ between interpreters, switchable at runtime
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Quirk {
+    BitshiftCopiesY,
+    ShiftVfFromVx,
+    JumpWithOffsetRegister,
+    UpdateIAfterStoreOrLoad,
 }
 
-impl Timers {
-    fn new() -> Self {
-        Self {
-            delay_timer: 0,
-            sound_timer: 0,
+impl Quirk {
+    const ALL: [Quirk; 4] = [
+        Quirk::BitshiftCopiesY,
+        Quirk::ShiftVfFromVx,
+        Quirk::JumpWithOffsetRegister,
+        Quirk::UpdateIAfterStoreOrLoad,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            Quirk::BitshiftCopiesY => "shift copies VY",
+            Quirk::ShiftVfFromVx => "shift VF from VX",
+            Quirk::JumpWithOffsetRegister => "BNNN uses VX",
+            Quirk::UpdateIAfterStoreOrLoad => "FX55/FX65 move I",
+        }
+    }
+
+    fn flag(self, config: &mut Config) -> &mut bool {
+        match self {
+            Quirk::BitshiftCopiesY => &mut config.bitshift_copies_y,
+            Quirk::ShiftVfFromVx => &mut config.shift_vf_from_vx,
+            Quirk::JumpWithOffsetRegister => &mut config.jump_with_offset_register,
+            Quirk::UpdateIAfterStoreOrLoad => &mut config.update_i_after_store_or_load,
         }
     }
 }
@@ -81,12 +235,60 @@ impl Timers {
 #[derive(Debug, Clone)]
 struct Shared {
     instrs_per_second: f64,
+    // when set, runs this many instructions in a burst every 60Hz frame
+    // instead of spacing them out, and instrs_per_second follows it
+    instrs_per_frame: Option<u32>,
+    // slow motion, below 1 stretches instructions and timers alike so they
+    // stay in step with each other
+    time_scale: f64,
     fast_forward: bool,
+    // fast_boot has recognised a splash screen wait and is speeding it up
+    fast_booting: bool,
+    paused: bool,
+    timers_paused: bool,
     instr_count: u32,
     count_start: Instant,
+    instrs_executed: u64,
+    frame_count: u64,
+    started: Instant,
+    rom_name: String,
+    controls: Vec<(u8, String)>,
+    // published by the compute thread for the hud, once each time round
+    // Chip8::run's loop so once per instruction or per frame's burst
+    pc: usize,
+    i: usize,
+    memory_len: usize,
+    program_start: usize,
+    next_instr: [u8; 2],
+    skipped_instrs: u64,
+    stack_depth: usize,
+    max_stack_depth: usize,
+    // how long the last frame's burst took to run, in IPF mode
+    burst_time: Duration,
+    opcodes_seen: u64,
+    // a copy of the compute thread's config, which is only ever changed
+    // through `edits` so it's republished then rather than every instruction
+    config: Config,
+    // set by the window, consumed by the compute thread
+    playlist_skip: isize,
+    load_request: Option<Rom>,
+    edits: Vec<Edit>,
+    run_until: Option<RunUntil>,
+    // only kept with debug_time_instrs, printed on exit
+    instr_times: Option<InstrTimes>,
 }
 
 impl Shared {
+    // time_scale, sped up while fast_boot is hurrying a splash screen along
+    fn run_time_scale(&self) -> f64 {
+        self.time_scale * tern!(self.fast_booting, FAST_BOOT_SPEEDUP, 1.0)
+    }
+
+    fn set_instrs_per_frame(&mut self, ipf: u32) {
+        self.instrs_per_frame = Some(ipf);
+        self.instrs_per_second = ipf as f64 * 60.0;
+    }
+
     fn reset_instr_count(&mut self) {
         self.instr_count = 0;
         self.count_start = Instant::now();
@@ -97,288 +299,655 @@ impl Default for Shared {
     fn default() -> Self {
         Self {
             instrs_per_second: 700.0,
+            instrs_per_frame: None,
+            time_scale: 1.0,
             fast_forward: false,
+            fast_booting: false,
+            paused: false,
+            timers_paused: false,
             instr_count: 0,
             count_start: Instant::now(),
+            instrs_executed: 0,
+            frame_count: 0,
+            started: Instant::now(),
+            rom_name: String::new(),
+            controls: vec![],
+            pc: 0,
+            i: 0,
+            memory_len: MemorySize::default().bytes(),
+            program_start: PROGRAM_START,
+            next_instr: [0; 2],
+            skipped_instrs: 0,
+            stack_depth: 0,
+            max_stack_depth: 0,
+            burst_time: Duration::ZERO,
+            opcodes_seen: 0,
+            config: Config::default(),
+            playlist_skip: 0,
+            load_request: None,
+            edits: vec![],
+            run_until: None,
+            instr_times: None,
         }
     }
 }
 
 #[derive(Debug, Clone)]
 struct Chip8 {
+    // owned here so execute_instr reads it without locking, the window sees
+    // Shared::config and changes it with Edit::ToggleQuirk
     config: Config,
     shared: Arc<Mutex<Shared>>,
     memory: Memory,
     pc: usize,
     i: usize,
     stack: Vec<usize>,
+    // deepest the stack has been since the rom was loaded
+    max_stack_depth: usize,
+    // bit n is set once Opcode::PATTERNS[n] has run since the rom was loaded
+    opcodes_seen: u64,
     registers: Registers,
+    // bit mask of the XO-CHIP planes that draws and clears apply to
+    planes: u8,
+    program_start: usize,
+    rom_len: usize,
+    // total instructions since the rom was loaded, unlike Shared::instr_count
+    instrs_executed: u64,
+    skipped_instrs: u64,
+    self_looped: bool,
+    // for fast_boot: whether the keypad has been read yet, and the address
+    // and instruction count of the last FX07
+    read_keys: bool,
+    last_delay_read: Option<(usize, u64)>,
+    // a copy of Shared::fast_booting, so FX07 doesn't lock to check it
+    fast_booting: bool,
+    // keys down the last time FX0A ran, to tell which were just pressed
+    keys_at_last_wait: u16,
+    // the key FX0A picked and is waiting to be released
+    waiting_key: Option<u8>,
+    // only once per run, even across reloads, so the csv stays one table
+    trace_header_written: bool,
+    // address and bytes of the last RECENT_INSTRS instructions, for fault
+    // reports
+    recent_instrs: VecDeque<(usize, [u8; 2])>,
+    // None unless something subscribed, so there's nothing to build or send
+    events: Option<Sender<Event>>,
+    quick_slot: Option<Snapshot>,
+    // where the quick slot is kept between runs, with --state
+    state_file: Option<PathBuf>,
+    // where fault reports go, with --fault-dir, and the addresses lenient
+    // mode has already written one for
+    fault_dir: Option<PathBuf>,
+    reported_faults: HashSet<usize>,
+    // seedable and serializable, so it can go in a Snapshot
+    rng: ChaCha12Rng,
+    // counts 60Hz frames for display_wait, None without a timer thread to
+    // count them so draws don't wait at all
+    vblank: Option<Vblank>,
+    // a snapshot from each of the last rewind_frames frames that ran, and
+    // the frame the newest is from
+    rewind: VecDeque<Snapshot>,
+    rewind_frame: u64,
+    playlist: Option<Playlist>,
     screen: Arc<Mutex<Screen>>,
     timers: Arc<Mutex<Timers>>,
+    // the same value as in `timers`, read without taking its lock
+    delay_timer: Arc<AtomicU8>,
     keys: Arc<Mutex<Keys>>,
 }
 
+// a frame counter the timer thread bumps every 60Hz tick, and signals
+type Vblank = Arc<(Mutex<u64>, Condvar)>;
+
+// memory and registers as the machine powers on, zeroed unless the config
+// asks for them randomized
+fn power_on_state(config: &Config) -> (Memory, Registers) {
+    let mut memory = Memory::new(config.memory_size);
+    let mut registers = Registers::new();
+    if let Some(seed) = config.random_init_seed {
+        let mut rng = StdRng::seed_from_u64(seed);
+        memory.randomize(&mut rng);
+        registers.randomize(&mut rng);
+    }
+    (memory, registers)
+}
+
+fn cxnn_rng(config: &Config) -> ChaCha12Rng {
+    match config.rng_seed {
+        Some(seed) => ChaCha12Rng::seed_from_u64(seed),
+        None => ChaCha12Rng::from_os_rng(),
+    }
+}
+
 impl Chip8 {
     fn new(config: Config) -> Self {
+        let (memory, registers) = power_on_state(&config);
+        let timers = Timers::new();
+        let rng = cxnn_rng(&config);
+        let shared = Shared {
+            memory_len: memory.len(),
+            config: config.clone(),
+            instr_times: config.debug_time_instrs.then(InstrTimes::new),
+            ..Shared::default()
+        };
         Self {
+            memory,
             config,
-            shared: Arc::new(Mutex::new(Shared::default())),
-            memory: Memory::new(),
+            shared: Arc::new(Mutex::new(shared)),
             pc: 0,
             i: 0,
             stack: vec![],
-            registers: Registers::new(),
+            max_stack_depth: 0,
+            opcodes_seen: 0,
+            registers,
+            planes: 0b01,
+            program_start: PROGRAM_START,
+            rom_len: 0,
+            instrs_executed: 0,
+            skipped_instrs: 0,
+            self_looped: false,
+            read_keys: false,
+            last_delay_read: None,
+            fast_booting: false,
+            keys_at_last_wait: 0,
+            waiting_key: None,
+            trace_header_written: false,
+            recent_instrs: VecDeque::with_capacity(RECENT_INSTRS),
+            events: None,
+            quick_slot: None,
+            state_file: None,
+            fault_dir: None,
+            reported_faults: HashSet::new(),
+            rng,
+            vblank: None,
+            rewind: VecDeque::new(),
+            rewind_frame: 0,
+            playlist: None,
             screen: Arc::new(Mutex::new(Screen::new())),
-            timers: Arc::new(Mutex::new(Timers::new())),
+            delay_timer: timers.delay_timer(),
+            timers: Arc::new(Mutex::new(timers)),
             keys: Arc::new(Mutex::new(Keys::default())),
         }
     }
 
+    fn load_rom(&mut self, rom: &Rom) -> Result<(), String> {
+        self.memory
+            .load_bytes_at(self.program_start, &rom.bytes)
+            .map_err(|e| format!("could not load {}: {e}", rom.name))?;
+        self.rom_len = rom.bytes.len();
+        Ok(())
+    }
+
+    // presets registers as (index, value) pairs
+    fn with_registers(mut self, values: &[(u8, u8)]) -> Self {
+        for &(x, v) in values {
+            self.registers.set(x, v);
+        }
+        self
+    }
+
+    fn poke(&mut self, addr: usize, byte: u8) {
+        self.memory.set(addr, byte);
+    }
+
+    // puts the machine back to its power-on state with `rom` loaded. a rom
+    // that doesn't fit leaves the current one running
+    fn reload(&mut self, rom: &Rom) -> Result<(), String> {
+        let (memory, registers) = power_on_state(&self.config);
+        let previous = mem::replace(&mut self.memory, memory);
+        if let Err(e) = self.load_rom(rom) {
+            self.memory = previous;
+            return Err(e);
+        }
+        self.registers = registers;
+        self.restart();
+        Ok(())
+    }
+
+    // starts the loaded program over, memory keeps whatever it wrote there
+    // apart from the font
+    fn reset(&mut self) {
+        self.memory.set_font();
+        self.registers = Registers::new();
+        self.restart();
+        let mut shared = self.shared.lock().unwrap();
+        shared.instrs_executed = 0;
+        shared.skipped_instrs = 0;
+        shared.reset_instr_count();
+        shared.frame_count = 0;
+        shared.started = Instant::now();
+    }
+
+    // everything but memory and registers, shared with reload and reset
+    fn restart(&mut self) {
+        self.rng = cxnn_rng(&self.config);
+        self.stack.clear();
+        self.max_stack_depth = 0;
+        self.opcodes_seen = 0;
+        self.i = 0;
+        self.planes = 0b01;
+        self.pc = self.program_start;
+        self.instrs_executed = 0;
+        self.skipped_instrs = 0;
+        self.reported_faults.clear();
+        self.self_looped = false;
+        self.read_keys = false;
+        self.last_delay_read = None;
+        self.set_fast_booting(false);
+        self.keys_at_last_wait = 0;
+        self.waiting_key = None;
+        self.rewind.clear();
+        self.recent_instrs.clear();
+        // one lock at a time, the window takes these on its own too
+        self.screen.lock().unwrap().clear(u8::MAX);
+        self.timers.lock().unwrap().reset();
+    }
+
+    // switches roms when the window asked to skip, or when the current rom has
+    // used up its slot or is spinning on a jump to itself
+    fn step_playlist(&mut self) {
+        let Some(playlist) = &mut self.playlist else {
+            return;
+        };
+        let mut shared = self.shared.lock().unwrap();
+        let by = match mem::take(&mut shared.playlist_skip) {
+            0 if self.self_looped || playlist.expired() => 1,
+            0 => return,
+            skip => skip,
+        };
+        let rom = playlist.advance(by).clone();
+        drop(shared);
+        if let Err(e) = self.reload(&rom) {
+            eprintln!("{e}");
+            return;
+        }
+        let mut shared = self.shared.lock().unwrap();
+        shared.rom_name = rom.name;
+        shared.controls = rom.controls;
+    }
+
+    fn in_program(&self, pc: usize) -> bool {
+        (self.program_start..self.program_start + self.rom_len).contains(&pc)
+    }
+
     fn execute_instr(&mut self) {
+        self.instrs_executed += 1;
         {
             let mut shared = self.shared.lock().unwrap();
+            shared.instrs_executed = self.instrs_executed;
             shared.instr_count += 1;
             if shared.instr_count > shared.instrs_per_second as u32 {
                 shared.reset_instr_count();
             }
         }
 
+        let instr_pc = self.pc;
+        if self.recent_instrs.len() == RECENT_INSTRS {
+            self.recent_instrs.pop_front();
+        }
+        self.recent_instrs.push_back((
+            instr_pc,
+            [self.memory.get(instr_pc), self.memory.get(instr_pc + 1)],
+        ));
+        let instr = self.fetch();
+        self.execute_at(instr, instr_pc);
+        if cfg!(debug_assertions) {
+            self.check_invariants(&instr, instr_pc);
+        }
+    }
+
+    // state no correct instruction should leave behind, only checked in debug
+    // builds since it runs after every instruction
+    fn check_invariants(&self, instr: &Instr, instr_pc: usize) {
+        let mut broken = vec![];
+        if !self.pc.is_multiple_of(2) {
+            broken.push(format!("pc {:#05X} is odd", self.pc));
+        }
+        if self.pc >= self.memory.len() {
+            broken.push(format!("pc {:#05X} is past the end of memory", self.pc));
+        }
+        if self.i >= self.memory.len() {
+            broken.push(format!("I {:#05X} is past the end of memory", self.i));
+        }
+        if self.stack.len() > STACK_LIMIT {
+            broken.push(format!("stack is {} deep", self.stack.len()));
+        }
+        for problem in broken {
+            eprintln!("invariant broken by {instr} at {instr_pc:#05X}: {problem}");
+        }
+    }
+
+    // whether a paused machine running until `target` should stop here
+    fn reached(&self, target: RunUntil) -> bool {
+        match target {
+            RunUntil::ReturnTo { address, depth } => self.pc == address && self.stack.len() < depth,
+            _ => target.matches(&Instr::new(
+                self.memory.get(self.pc),
+                self.memory.get(self.pc + 1),
+            )),
+        }
+    }
+
+    // the instruction at pc, moving pc past it
+    fn fetch(&mut self) -> Instr {
+        if self.config.strict_pc_alignment && !self.pc.is_multiple_of(2) {
+            panic!("fetching from odd address {:#05X}", self.pc);
+        }
         let instr = Instr::new(self.memory.get(self.pc), self.memory.get(self.pc + 1));
-        self.pc += 2;
+        self.pc = self.memory.wrap(self.pc + 2);
+        instr
+    }
+
+    // pc past the next instruction, wrapping at the end of memory like fetch
+    fn skip_next(&mut self) {
+        self.pc = self.memory.wrap(self.pc + 2);
+    }
 
+    // runs one instruction from the given bytes instead of from memory. pc
+    // isn't advanced past it first, but jumps, calls and skips still act on pc
+    fn execute(&mut self, b1: u8, b2: u8) {
+        self.execute_at(Instr::new(b1, b2), self.pc);
+    }
+
+    // `instr_pc` is where `instr` is treated as having come from
+    fn execute_at(&mut self, instr: Instr, instr_pc: usize) {
         if self.config.debug_print_instrs {
-            println!("running {instr} at address {:#05X}", self.pc);
+            match self.config.trace_format {
+                TraceFormat::Text => println!(
+                    "#{} running {instr} at address {instr_pc:#05X}",
+                    self.instrs_executed
+                ),
+                TraceFormat::Csv => self.trace_csv(instr_pc, &instr),
+            }
         }
 
-        match instr.as_nibbles() {
-            // Clear screen
-            (0x0, 0x0, 0xE, 0x0) => {
-                self.screen.lock().unwrap().clear();
-            }
+        if RunUntil::KeyRead.matches(&instr) {
+            self.read_keys = true;
+            self.set_fast_booting(false);
+        }
 
-            // Return from subroutine
-            (0x0, 0x0, 0xE, 0xE) => {
-                self.pc = self.stack.pop().unwrap();
+        match Opcode::decode(&instr) {
+            Some(op) => {
+                self.opcodes_seen |= 1 << op.index();
+                self.execute_opcode(op, &instr, instr_pc);
             }
+            None if self.config.lenient_opcodes => self.skip_instr(&instr, instr_pc),
+            // 8XY8 to 8XYD and 8XYF aren't defined by any variant
+            None if instr.as_nibbles().0 == 0x8 => panic!(
+                "unknown arithmetic instruction {instr}, there is no 8XY{:X} operation",
+                instr.as_nibbles().3
+            ),
+            None => panic!("unknown instruction {instr}"),
+        }
+
+        if self.config.debug_log_pc_range && self.in_program(instr_pc) && !self.in_program(self.pc)
+        {
+            println!(
+                "pc left the program ({:#05X}..{:#05X}): {instr} at address {instr_pc:#05X} moved it to {:#05X}",
+                self.program_start,
+                self.program_start + self.rom_len,
+                self.pc
+            );
+        }
+    }
 
-            // Execute machine code
-            (0x0, _, _, _) => {
-                unimplemented!("This instruction executes machine code for a different computer")
+    // `instr` is what `op` was decoded from, for logs and errors
+    fn execute_opcode(&mut self, op: Opcode, instr: &Instr, instr_pc: usize) {
+        match op {
+            Opcode::ClearScreen => {
+                self.screen.lock().unwrap().clear(self.planes);
             }
 
-            // Jump
-            (0x1, _, _, _) => {
-                self.pc = instr.as_address();
+            Opcode::Return => match self.stack.pop() {
+                Some(pc) => self.pc = pc,
+                None if self.config.lenient_opcodes => self.skip_instr(instr, instr_pc),
+                None => panic!("{instr} at {instr_pc:#05X} returns with nothing on the stack"),
+            },
+
+            Opcode::Sys(_) if self.config.lenient_opcodes => self.skip_instr(instr, instr_pc),
+            Opcode::Sys(address) => panic!(
+                "{instr} at {instr_pc:#05X} calls machine code at {address:#05X}, which only the original computer could run"
+            ),
+
+            Opcode::Jump(address) => {
+                self.pc = address as usize;
+                self.self_looped = self.pc == instr_pc;
             }
 
-            // Jump to subroutine
-            (0x2, _, _, _) => {
+            Opcode::Call(address) => {
+                self.emit(Event::SubroutineCall {
+                    from: instr_pc,
+                    to: address as usize,
+                });
                 self.stack.push(self.pc);
-                self.pc = instr.as_address();
+                self.max_stack_depth = self.max_stack_depth.max(self.stack.len());
+                self.pc = address as usize;
             }
 
-            // Skip if equal
-            (0x3, x, _, _) => {
-                if self.registers.get(x) == instr.as_u8() {
-                    self.pc += 2;
+            Opcode::SkipEqImmediate { x, value } => {
+                if self.registers.get(x) == value {
+                    self.skip_next();
                 }
             }
 
-            // Skip if not equal
-            (0x4, x, _, _) => {
-                if self.registers.get(x) != instr.as_u8() {
-                    self.pc += 2;
+            Opcode::SkipNeImmediate { x, value } => {
+                if self.registers.get(x) != value {
+                    self.skip_next();
                 }
             }
 
-            // Skip if registers equal
-            (0x5, x, y, 0x0) => {
+            Opcode::SkipEqRegister { x, y } => {
                 if self.registers.get(x) == self.registers.get(y) {
-                    self.pc += 2;
+                    self.skip_next();
                 }
             }
 
-            // Set immediate
-            (0x6, x, _, _) => {
-                self.registers.set(x, instr.as_u8());
+            Opcode::SetImmediate { x, value } => {
+                self.registers.set(x, value);
             }
 
-            // Add
-            (0x7, x, _, _) => {
-                *self.registers.get_mut(x) = self.registers.get(x).wrapping_add(instr.as_u8());
+            Opcode::AddImmediate { x, value } => {
+                *self.registers.get_mut(x) = self.registers.get(x).wrapping_add(value);
             }
 
-            // Copy
-            (0x8, x, y, 0x0) => {
+            Opcode::Copy { x, y } => {
                 *self.registers.get_mut(x) = self.registers.get(y);
             }
 
-            // Binary OR
-            (0x8, x, y, 0x1) => {
+            Opcode::Or { x, y } => {
                 *self.registers.get_mut(x) |= self.registers.get(y);
-                self.registers.set(0xF, 0);
+                self.set_vf(0, instr, instr_pc);
             }
 
-            // Binary AND
-            (0x8, x, y, 0x2) => {
+            Opcode::And { x, y } => {
                 *self.registers.get_mut(x) &= self.registers.get(y);
-                self.registers.set(0xF, 0);
+                self.set_vf(0, instr, instr_pc);
             }
 
-            // Binary XOR
-            (0x8, x, y, 0x3) => {
+            Opcode::Xor { x, y } => {
                 *self.registers.get_mut(x) ^= self.registers.get(y);
-                self.registers.set(0xF, 0);
+                self.set_vf(0, instr, instr_pc);
             }
 
-            // Add with carry
-            (0x8, x, y, 0x4) => {
+            // with carry
+            Opcode::Add { x, y } => {
                 let res = self.registers.get(x).overflowing_add(self.registers.get(y));
                 self.registers.set(x, res.0);
-                self.registers.set(0xF, res.1.into());
+                self.set_vf(res.1.into(), instr, instr_pc);
             }
 
-            // Subtract with carry
-            (0x8, x, y, 0x5) => {
+            // with carry
+            Opcode::Sub { x, y } => {
                 let res = self.registers.get(x).overflowing_sub(self.registers.get(y));
                 self.registers.set(x, res.0);
-                self.registers.set(0xF, (!res.1).into());
+                self.set_vf((!res.1).into(), instr, instr_pc);
             }
 
-            // Shift right
-            (0x8, x, y, 0x6) => {
-                if self.config.bitshift_copies_y {
-                    *self.registers.get_mut(y) = self.registers.get(x);
-                }
-                let n = self.registers.get(x);
-                self.registers.set(x, n.wrapping_shr(1));
-                self.registers.set(0xF, n & 1);
-            }
+            Opcode::ShiftRight { x, y } => self.shift(x, y, instr, instr_pc, |n| (n >> 1, n & 1)),
 
-            // Subtract from with carry
-            (0x8, x, y, 0x7) => {
+            // with carry
+            Opcode::SubFrom { x, y } => {
                 let res = self.registers.get(y).overflowing_sub(self.registers.get(x));
                 self.registers.set(x, res.0);
-                self.registers.set(0xF, (!res.1).into());
+                self.set_vf((!res.1).into(), instr, instr_pc);
             }
 
-            // Shift left
-            (0x8, x, y, 0xE) => {
-                if self.config.bitshift_copies_y {
-                    *self.registers.get_mut(y) = self.registers.get(x);
-                }
-                let n = self.registers.get(x);
-                self.registers.set(x, n.wrapping_shl(1));
-                self.registers.set(0xF, (n & (1 << 7) != 0).into());
-            }
+            Opcode::ShiftLeft { x, y } => self.shift(x, y, instr, instr_pc, |n| (n << 1, n >> 7)),
 
-            // Skip if registers not equal
-            (0x9, x, y, 0x0) => {
+            Opcode::SkipNeRegister { x, y } => {
                 if self.registers.get(x) != self.registers.get(y) {
-                    self.pc += 2;
+                    self.skip_next();
                 }
             }
 
-            // Set index
-            (0xA, _, _, _) => self.i = instr.as_address(),
-
-            // Jump with offset
-            (0xB, x, _, _) => {
-                self.pc = instr.as_address()
-                    + self
-                        .registers
-                        .get(tern!(self.config.jump_with_offset_register, x, 0))
-                        as usize;
+            Opcode::SetIndex(address) => self.i = address as usize,
+
+            Opcode::JumpWithOffset { x, address } => {
+                let offset = self
+                    .registers
+                    .get(tern!(self.config.jump_with_offset_register, x, 0));
+                let target = address as usize + offset as usize;
+                // past the end wraps like any other address, unless
+                // strict_memory_bounds makes it an error
+                if self.config.strict_memory_bounds && target >= self.memory.len() {
+                    panic!(
+                        "{instr} at {instr_pc:#05X} jumps past the end of memory to {target:#05X}"
+                    );
+                }
+                self.pc = self.memory.wrap(target);
             }
 
-            // Random
-            (0xC, x, _, _) => {
-                let r = ::rand::random::<u8>() & instr.as_u8();
+            Opcode::Random { x, mask } => {
+                let r = self.rng.random::<u8>() & mask;
                 self.registers.set(x, r);
             }
 
-            // Display
-            (0xD, x, y, n) => {
+            Opcode::Display { x, y, n } => {
+                if self.config.display_wait {
+                    self.wait_for_vblank();
+                }
+                // only the starting position wraps, so VX = 70 draws from
+                // column 6. the sprite itself is clipped at the right and
+                // bottom edges, or wraps onto the other side with
+                // sprite_wrapping
                 let x = self.registers.get(x) % 64;
                 let y = self.registers.get(y) % 32;
-                self.registers.set(0xF, 0);
+                let mut collision = false;
 
+                // with both planes selected the second plane's rows follow
+                // the first's in memory
+                let mut sprite_addr = self.i;
                 let mut display = self.screen.lock().unwrap();
-                for row in 0..n {
-                    if y + row >= 32 {
-                        break;
-                    }
-                    let sprite_data = self.memory.get(self.i + row as usize);
-                    for i in 0..8 {
-                        if x + i >= 64 {
+                for plane in (0..PLANES).filter(|p| self.planes & (1 << p) != 0) {
+                    for row in 0..n {
+                        if y + row >= 32 && !self.config.sprite_wrapping {
                             break;
                         }
-                        let sprite_pixel = (sprite_data & (1 << (7 - i))) != 0;
-                        if sprite_pixel && !display.toggle(x + i, y + row) {
-                            self.registers.set(0xF, 1);
+                        let sprite_data = self.memory.get(sprite_addr + row as usize);
+                        for i in 0..8 {
+                            if x + i >= 64 && !self.config.sprite_wrapping {
+                                break;
+                            }
+                            // the top bit is the leftmost pixel
+                            let sprite_pixel = (sprite_data & (1 << (7 - i))) != 0;
+                            let (px, py) = ((x + i) % 64, (y + row) % 32);
+                            if sprite_pixel && !display.toggle(plane, px, py) {
+                                collision = true;
+                            }
                         }
                     }
+                    sprite_addr += n as usize;
                 }
+                drop(display);
+                self.set_vf(collision.into(), instr, instr_pc);
+                self.emit(Event::Draw {
+                    x,
+                    y,
+                    rows: n,
+                    collision,
+                });
             }
 
-            // Skip if pressed
-            (0xE, x, 0x9, 0xE) => {
-                if self.keys.lock().unwrap().get(self.registers.get(x)) {
-                    self.pc += 2;
+            Opcode::SkipPressed { x } => {
+                if self.keys.lock().unwrap().get(self.key_index(x, instr)) {
+                    self.skip_next();
                 }
             }
-            // Skip if not pressed
-            (0xE, x, 0xA, 0x1) => {
-                if !self.keys.lock().unwrap().get(self.registers.get(x)) {
-                    self.pc += 2;
+            Opcode::SkipNotPressed { x } => {
+                if !self.keys.lock().unwrap().get(self.key_index(x, instr)) {
+                    self.skip_next();
                 }
             }
 
-            // Set delay timer
-            (0xF, x, 0x0, 0x7) => {
-                let t = self.timers.lock().unwrap();
-                self.registers.set(x, t.delay_timer);
+            Opcode::SelectPlanes(n) => {
+                self.planes = n & 0b11;
             }
 
-            // Get key
-            (0xF, x, 0x0, 0xA) => {
-                if let Some(idx) = self.keys.lock().unwrap().iter().position(|k| *k) {
-                    // key was pressed, store its index in vx
-                    self.registers.set(x, idx as u8);
-                } else {
-                    // no keys pressed
-                    self.pc -= 2;
+            Opcode::ReadDelay { x } => {
+                let delay = self.delay_timer.load(atomic::Ordering::Relaxed);
+                if self.config.fast_boot && !self.read_keys {
+                    self.detect_splash_wait(instr_pc, delay);
                 }
+                self.registers.set(x, delay);
             }
 
-            // Get delay timer
-            (0xF, x, 0x1, 0x5) => {
-                let mut t = self.timers.lock().unwrap();
-                t.delay_timer = self.registers.get(x);
+            Opcode::WaitKey { x } => {
+                let down = self.keys.lock().unwrap().as_u16();
+                let pressed = down & !self.keys_at_last_wait;
+                self.keys_at_last_wait = down;
+                let lowest = |keys: u16| (keys != 0).then(|| keys.trailing_zeros() as u8);
+                // the key is picked when it goes down but only taken once
+                // it's let go, so holding it doesn't count as several presses.
+                // until then this runs again
+                match self.waiting_key {
+                    Some(key) if down & (1 << key) == 0 => {
+                        self.registers.set(x, key);
+                        self.waiting_key = None;
+                    }
+                    Some(_) => self.pc = instr_pc,
+                    None => {
+                        self.waiting_key = match self.config.wait_key_policy {
+                            WaitKeyPolicy::Lowest => lowest(down),
+                            WaitKeyPolicy::Newest => lowest(pressed).or(lowest(down)),
+                            WaitKeyPolicy::OnlyOne => {
+                                lowest(down).filter(|_| down.count_ones() == 1)
+                            }
+                        };
+                        self.pc = instr_pc;
+                    }
+                }
             }
 
-            // Set sound timer
-            (0xF, x, 0x1, 0x8) => {
-                self.timers.lock().unwrap().sound_timer = self.registers.get(x);
+            Opcode::SetDelay { x } => {
+                self.delay_timer
+                    .store(self.registers.get(x), atomic::Ordering::Relaxed);
             }
 
-            // Add to index
-            (0xF, x, 0x1, 0xE) => {
+            Opcode::SetSound { x } => {
+                let ticks = self.registers.get(x);
+                self.timers.lock().unwrap().set_sound_timer(ticks);
+                if ticks != 0 {
+                    self.emit(Event::SoundStart { ticks });
+                }
+            }
+
+            Opcode::AddIndex { x } => {
                 self.i += self.registers.get(x) as usize;
-                if self.i >= 0x1000 {
-                    self.i %= 0x1000;
-                    self.registers.set(0xF, 1);
+                if self.i >= self.memory.len() {
+                    self.i = self.memory.wrap(self.i);
+                    self.set_vf(1, instr, instr_pc);
                 }
             }
 
-            // Font character
-            (0xF, x, 0x2, 0x9) => {
+            Opcode::FontCharacter { x } => {
                 let ch = self.registers.get(x) & 0x0F;
-                self.i = 0x50 + (ch as usize * 5);
+                self.i = FONT_START + (ch as usize * 5);
             }
 
-            // BCD
-            (0xF, x, 0x3, 0x3) => {
+            Opcode::Bcd { x } => {
+                // most significant digit first: hundreds at I, tens at I+1,
+                // ones at I+2
+                self.check_span(instr, 3);
                 let mut n = self.registers.get(x);
                 self.memory.set(self.i, n / 100);
                 n %= 100;
@@ -386,110 +955,1420 @@ impl Chip8 {
                 self.memory.set(self.i + 2, n % 10);
             }
 
-            // Store memory
-            (0xF, x, 0x5, 0x5) => {
+            Opcode::Store { x } => {
                 for dest in 0..=x {
                     self.memory
                         .set(self.i + dest as usize, self.registers.get(dest));
                 }
                 if self.config.update_i_after_store_or_load {
-                    self.i += x as usize + 1;
+                    self.i = self.memory.wrap(self.i + x as usize + 1);
                 }
             }
 
-            // Load memory
-            (0xF, x, 0x6, 0x5) => {
+            Opcode::Load { x } => {
                 for dest in 0..=x {
                     self.registers
                         .set(dest, self.memory.get(self.i + dest as usize));
                 }
                 if self.config.update_i_after_store_or_load {
-                    self.i += x as usize + 1;
+                    self.i = self.memory.wrap(self.i + x as usize + 1);
                 }
             }
+        }
+    }
+
+    // 8XY6/8XYE, `op` returns the shifted value and the bit shifted out.
+    // with bitshift_copies_y (COSMAC) VY is shifted into VX, otherwise (SUPER-CHIP)
+    // VX is shifted in place. VF normally gets the bit shifted out of that
+    // operand, shift_vf_from_vx takes it from the original VX instead.
+    fn shift(&mut self, x: u8, y: u8, instr: &Instr, instr_pc: usize, op: fn(u8) -> (u8, u8)) {
+        let vx = self.registers.get(x);
+        let operand = tern!(self.config.bitshift_copies_y, self.registers.get(y), vx);
+        let (result, shifted_out) = op(operand);
+        let flag = tern!(self.config.shift_vf_from_vx, op(vx).1, shifted_out);
+        self.registers.set(x, result);
+        self.set_vf(flag, instr, instr_pc);
+    }
 
-            _ => panic!("unknown instruction {instr}"),
+    // one csv row per instruction, with a header before the first
+    fn trace_csv(&mut self, pc: usize, instr: &Instr) {
+        if !self.trace_header_written {
+            let registers = (0..0x10).map(|x| format!(",V{x:X}")).collect::<String>();
+            println!("pc,opcode,mnemonic{registers},I,sp");
+            self.trace_header_written = true;
         }
+        let registers = (0..0x10)
+            .map(|x| format!(",{}", self.registers.get(x)))
+            .collect::<String>();
+        println!(
+            "{pc:#05X},{instr},\"{}\"{registers},{:#05X},{}",
+            instr.disassemble_with(&self.config),
+            self.i,
+            self.stack.len()
+        );
     }
 
-    fn run_at(&mut self, pc: usize) -> ! {
-        self.pc = pc;
+    // keeps a snapshot once per window frame that has instructions run in it
+    fn record_rewind(&mut self, frame: u64) {
+        if self.config.rewind_frames == 0 || frame == self.rewind_frame {
+            return;
+        }
+        self.rewind_frame = frame;
+        if self.rewind.len() == self.config.rewind_frames {
+            self.rewind.pop_front();
+        }
+        self.rewind.push_back(self.snapshot());
+    }
+
+    // blocks until the timer thread starts the next frame, unless fast
+    // forwarding
+    fn wait_for_vblank(&self) {
+        let Some(vblank) = &self.vblank else {
+            return;
+        };
+        if self.shared.lock().unwrap().fast_forward {
+            return;
+        }
+        let (frames, next_frame) = &**vblank;
+        let mut frame = frames.lock().unwrap();
+        let start = *frame;
+        while *frame == start {
+            frame = next_frame.wait(frame).unwrap();
+        }
+    }
 
-        let mut frame_delay;
+    // `len` bytes of memory from `start` as (address, mnemonic), two bytes at
+    // a time. data comes out as DB since there's no telling it from code here
+    fn dump_disassembly(&self, start: usize, len: usize) -> Vec<(usize, String)> {
+        (start..start + len)
+            .step_by(2)
+            .map(|addr| {
+                let instr = Instr::new(self.memory.get(addr), self.memory.get(addr + 1));
+                (addr, instr.disassemble_with(&self.config))
+            })
+            .collect()
+    }
+
+    // the `rows` bytes at I drawn the way DXYN would draw them
+    fn sprite_ascii(&self, rows: u8) -> String {
+        let mut out = format!("sprite at I = {:#05X}, {rows} rows:\n", self.i);
+        for row in 0..rows as usize {
+            let addr = self.i + row;
+            let byte = self.memory.get(addr);
+            let pixels = (0..8)
+                .map(|bit| tern!(byte & (0x80 >> bit) != 0, '#', '.'))
+                .collect::<String>();
+            out += &format!(
+                "    {:#05X}  {pixels}  {byte:#04X}\n",
+                self.memory.wrap(addr)
+            );
+        }
+        out
+    }
+
+    // VX as a key for EX9E/EXA1. only the low nibble counts unless
+    // strict_key_index makes anything past 0xF an error
+    fn key_index(&self, x: u8, instr: &Instr) -> u8 {
+        let key = self.registers.get(x);
+        if self.config.strict_key_index && key > 0xF {
+            panic!("{instr} reads key {key:#04X} from V{x:X}, past the last key 0xF");
+        }
+        key & 0xF
+    }
+
+    // writes of `len` bytes at I wrap around the end of memory, unless
+    // strict_memory_bounds makes that an error
+    fn check_span(&self, instr: &Instr, len: usize) {
+        if self.config.strict_memory_bounds && self.i + len > self.memory.len() {
+            panic!(
+                "{instr} at I = {:#05X} would write past the end of memory",
+                self.i
+            );
+        }
+    }
+
+    // Splash screens spin on a short loop reading the delay timer until it
+    // runs out. Two reads from the same FX07 within FAST_BOOT_LOOP_LEN
+    // instructions is taken as one of those, and everything runs
+    // FAST_BOOT_SPEEDUP times faster, timers included, until that FX07 reads
+    // 0. The rom still sees every value the timer goes through. Only done
+    // before the program first reads the keypad, since after that the delay
+    // timer is usually pacing the game itself.
+    fn detect_splash_wait(&mut self, pc: usize, delay: u8) {
+        let looping = self.last_delay_read.is_some_and(|(last_pc, at)| {
+            last_pc == pc && self.instrs_executed - at <= FAST_BOOT_LOOP_LEN
+        });
+        self.last_delay_read = Some((pc, self.instrs_executed));
+        self.set_fast_booting(looping && delay != 0);
+    }
+
+    fn set_fast_booting(&mut self, fast_booting: bool) {
+        if self.fast_booting != fast_booting {
+            self.fast_booting = fast_booting;
+            self.shared.lock().unwrap().fast_booting = fast_booting;
+        }
+    }
+
+    fn emit(&self, event: Event) {
+        if let Some(events) = &self.events {
+            // a subscriber that's gone away just stops hearing about them
+            let _ = events.send(event);
+        }
+    }
+
+    // every event from now on, on the returned channel
+    fn subscribe(&mut self) -> Receiver<Event> {
+        let (sender, receiver) = mpsc::channel();
+        self.events = Some(sender);
+        receiver
+    }
+
+    // lenient mode treats 0NNN, unknown opcodes and returns with an empty
+    // stack as no-ops, but keeps count since a lot of them usually means the
+    // wrong quirks or a decode bug
+    fn skip_instr(&mut self, instr: &Instr, instr_pc: usize) {
+        self.emit(Event::Fault {
+            pc: instr_pc,
+            instr: instr.as_bytes(),
+        });
+        if self.config.debug_print_instrs && self.config.trace_format == TraceFormat::Text {
+            println!("skipping {instr}");
+        }
+        self.skipped_instrs += 1;
+        // once per address, a rom stuck looping over a bad instruction would
+        // otherwise write a report every time around
+        if let Some(dir) = &self.fault_dir
+            && self.reported_faults.insert(instr_pc)
         {
-            let options = self.shared.lock().unwrap();
-            frame_delay = 1.0 / options.instrs_per_second;
+            fault::report(self, dir, &format!("skipped {instr} at {instr_pc:#05X}"));
         }
+    }
 
-        let mut next_time = Instant::now() + Duration::from_secs_f64(frame_delay);
-        loop {
+    // runs as fast as possible for `frames` 60Hz frames, ticking the timers
+    // itself instead of leaving it to the timer thread
+    fn run_frames(&mut self, frames: u32) {
+        let per_frame = (self.shared.lock().unwrap().instrs_per_second / 60.0).round() as u32;
+        let sound_hz = self.timers.lock().unwrap().sound_hz;
+        // sound ticks due by the end of a frame, so rates that aren't a
+        // multiple of 60 still come out right over a run
+        let sound_ticks = |frame: u32| (frame as f64 * sound_hz / 60.0).floor() as u32;
+        for frame in 0..frames {
+            for _ in 0..per_frame {
+                self.execute_instr();
+            }
+            let mut timers = self.timers.lock().unwrap();
+            timers.tick_delay();
+            for _ in sound_ticks(frame)..sound_ticks(frame + 1) {
+                timers.tick_sound();
+            }
+        }
+    }
+
+    // runs up to `max_instrs` from `pc` as fast as possible, stopping early at
+    // a jump to itself since that's how most test roms end. the timers tick
+    // once per frame's worth of instructions. returns how many ran
+    fn run_headless(&mut self, pc: usize, max_instrs: usize) -> usize {
+        self.pc = pc;
+        self.self_looped = false;
+        let per_frame = (self.shared.lock().unwrap().instrs_per_second / 60.0).round() as usize;
+        for n in 1..=max_instrs {
             self.execute_instr();
+            if self.self_looped {
+                return n;
+            }
+            if n.is_multiple_of(per_frame.max(1)) {
+                let mut timers = self.timers.lock().unwrap();
+                timers.tick_delay();
+                timers.tick_sound();
+            }
+        }
+        max_instrs
+    }
+
+    fn set_vf(&mut self, v: u8, instr: &Instr, instr_pc: usize) {
+        if self.config.debug_log_vf {
+            println!("VF = {v:#04X} by {instr} at address {instr_pc:#05X}");
+        }
+        self.emit(Event::FlagWrite {
+            pc: instr_pc,
+            value: v,
+        });
+        self.registers.set_vf(v);
+    }
 
+    // runs from the current pc
+    fn run(&mut self) -> ! {
+        let mut pacer = Pacer::new(Instant::now());
+        loop {
+            let paused;
+            let run_until;
             let fast_forward;
+            let instrs_per_second;
+            let instrs_per_frame;
+            let time_scale;
+            let load;
+            let edits;
+            let frame;
             {
-                let options = self.shared.lock().unwrap();
+                let mut options = self.shared.lock().unwrap();
+                load = options.load_request.take();
+                edits = mem::take(&mut options.edits);
+                options.pc = self.pc;
+                options.i = self.i;
+                options.next_instr = [self.memory.get(self.pc), self.memory.get(self.pc + 1)];
+                options.skipped_instrs = self.skipped_instrs;
+                options.stack_depth = self.stack.len();
+                options.max_stack_depth = self.max_stack_depth;
+                options.opcodes_seen = self.opcodes_seen;
+                paused = options.paused;
+                run_until = options.run_until;
                 fast_forward = options.fast_forward;
-                frame_delay = 1.0 / options.instrs_per_second;
+                instrs_per_second = options.instrs_per_second;
+                instrs_per_frame = options.instrs_per_frame;
+                time_scale = options.run_time_scale();
+                frame = options.frame_count;
+            }
+
+            if let Some(rom) = load {
+                // a rom picked by hand takes over from the playlist
+                match self.reload(&rom) {
+                    Ok(()) => {
+                        self.playlist = None;
+                        let mut shared = self.shared.lock().unwrap();
+                        shared.rom_name = rom.name;
+                        shared.controls = rom.controls;
+                    }
+                    Err(e) => eprintln!("{e}"),
+                }
+            }
+            let mut rewound = false;
+            for edit in edits {
+                match edit {
+                    Edit::Warp(addr) => self.pc = addr,
+                    Edit::SetRegister(x, v) => self.registers.set(x, v),
+                    Edit::Poke(addr, v) => self.poke(addr, v),
+                    Edit::ToggleQuirk(quirk) => {
+                        let flag = quirk.flag(&mut self.config);
+                        *flag = !*flag;
+                        self.shared.lock().unwrap().config = self.config.clone();
+                    }
+                    Edit::DumpSprite(rows) => print!("{}", self.sprite_ascii(rows)),
+                    Edit::Disassemble(start, len) => {
+                        for (addr, text) in self.dump_disassembly(start, len) {
+                            println!("{addr:#05X}  {text}");
+                        }
+                    }
+                    Edit::Execute(b1, b2) => self.execute(b1, b2),
+                    Edit::SaveSnapshot => {
+                        let snapshot = self.snapshot();
+                        if let Some(path) = &self.state_file
+                            && let Err(e) = snapshot.save(path)
+                        {
+                            eprintln!("{e}");
+                        }
+                        self.quick_slot = Some(snapshot);
+                    }
+                    Edit::DiffSnapshot => match &self.quick_slot {
+                        Some(snapshot) => {
+                            let lines = snapshot.diff(&self.snapshot());
+                            if lines.is_empty() {
+                                println!("nothing has changed since the snapshot");
+                            }
+                            for line in lines {
+                                println!("{line}");
+                            }
+                        }
+                        None => println!("there's no snapshot to compare against"),
+                    },
+                    Edit::LoadSnapshot => {
+                        if let Some(snapshot) = self.quick_slot.clone() {
+                            if snapshot.config() != &self.config {
+                                eprintln!(
+                                    "the snapshot was saved with a different config, switching back to it"
+                                );
+                            }
+                            self.restore(snapshot);
+                        }
+                    }
+                    // pauses too, or the frames run after it would be
+                    // recorded again and the next press would only undo those
+                    Edit::Rewind => {
+                        if let Some(snapshot) = self.rewind.pop_back() {
+                            self.restore(snapshot);
+                            self.rewind_frame = frame;
+                            self.shared.lock().unwrap().paused = true;
+                            rewound = true;
+                        }
+                    }
+                    Edit::Reset => self.reset(),
+                }
+            }
+            if rewound {
+                continue;
             }
 
-            if !fast_forward {
-                // while next_time > Instant::now() {}
-                thread::sleep(next_time - Instant::now());
-                next_time += Duration::from_secs_f64(frame_delay);
+            if let Some(RunUntil::Return) = run_until.filter(|_| paused) {
+                // nothing to run to outside of a subroutine
+                let target = self.stack.last().map(|&address| RunUntil::ReturnTo {
+                    address,
+                    depth: self.stack.len(),
+                });
+                self.shared.lock().unwrap().run_until = target;
+                continue;
             }
+            if let Some(target) = run_until.filter(|_| paused) {
+                // always run at least one, so asking again moves on to the
+                // next match instead of stopping where it already is
+                self.execute_instr();
+                self.record_rewind(frame);
+                if self.reached(target) {
+                    self.shared.lock().unwrap().run_until = None;
+                }
+                continue;
+            }
+            if paused {
+                thread::sleep(PAUSE_POLL_INTERVAL);
+                pacer.restart(Instant::now());
+                continue;
+            }
+
+            if self.config.debug_time_instrs {
+                let start = Instant::now();
+                self.execute_instr();
+                let time = start.elapsed();
+                if let Some(times) = &mut self.shared.lock().unwrap().instr_times {
+                    times.record(time);
+                }
+            } else {
+                self.execute_instr();
+            }
+            self.record_rewind(frame);
+            self.step_playlist();
+
+            let now = Instant::now();
+            let (next_time, burst_time) = pacer.after_instr(
+                now,
+                instrs_per_second,
+                instrs_per_frame,
+                time_scale,
+                fast_forward,
+            );
+            if let Some(burst_time) = burst_time {
+                self.shared.lock().unwrap().burst_time = burst_time;
+            }
+            thread::sleep(next_time.saturating_duration_since(now));
         }
     }
 }
 
-use window::window_main;
+use args::Args;
+use audio::{Beeper, DEFAULT_VOLUME};
+use builder::{Chip8Builder, Handles};
+use hotkeys::Hotkeys;
+use macroquad::input::KeyCode;
+use prefs::Prefs;
+use window::{KEY_MAP, RenderConfig, window_main};
 
 use crate::keys::Keys;
 
-#[macroquad::main("CHIP-8")]
-async fn main() {
-    let config = Config {
-        ..Default::default()
+// flushes whatever's been written out before the panic message, so a crash in
+// the compute thread doesn't lose the end of a trace
+fn install_panic_flush() {
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        let _ = io::stdout().flush();
+        default_hook(info);
+    }));
+}
+
+fn main() {
+    install_panic_flush();
+
+    let mut args = args::parse().unwrap_or_else(|e| {
+        eprintln!("{e}");
+        process::exit(2);
+    });
+
+    if args.selftest {
+        process::exit(tern!(headless::selftest(), 0, 1));
+    }
+
+    let mut roms = vec![];
+    for path in &args.roms {
+        let rom = Rom::load(path).unwrap_or_else(|e| {
+            eprintln!("could not read {}: {e}", path.display());
+            process::exit(1);
+        });
+        roms.push(rom);
+    }
+    // sprites go in the same way as pokes, byte by byte
+    for (addr, path) in args.sprites.clone() {
+        let bytes = sprite::from_png(&path).unwrap_or_else(|e| {
+            eprintln!("{e}");
+            process::exit(1);
+        });
+        args.pokes.extend((addr..).zip(bytes));
+    }
+    if roms.is_empty() && args.demo {
+        roms.push(Rom::demo());
+    } else if roms.is_empty() {
+        roms.push(Rom {
+            name: "snake.ch8".into(),
+            bytes: include_bytes!("../programs/games/snake.ch8").to_vec(),
+            controls: vec![],
+        });
+    }
+
+    if args.disassemble {
+        for rom in &roms {
+            println!("; {}", rom.name);
+            print!(
+                "{}",
+                disasm::disassemble_flow(&rom.bytes, args.start, args.start)
+            );
+        }
+        return;
+    }
+
+    if args.analyze {
+        for rom in &roms {
+            println!("; {}", rom.name);
+            print!(
+                "{}",
+                disasm::opcode_histogram(&rom.bytes, args.start, args.start)
+            );
+        }
+        return;
+    }
+
+    if args.memory_map {
+        let (chip8, _) = build_chip8(&args, roms);
+        let cursor = args.memory_map_at.unwrap_or(chip8.pc);
+        print!("{}", memmap::memory_map(&chip8, cursor));
+        return;
+    }
+
+    if let Some(max_instrs) = args.headless {
+        let (mut chip8, _) = build_chip8(&args, roms);
+        print!("{}", headless::run(&mut chip8, args.start, max_instrs));
+        return;
+    }
+
+    if args.capture {
+        let (mut chip8, _) = build_chip8(&args, roms);
+        // a bad rom panics in the interpreter, the hook has already printed why
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            headless::capture(&mut chip8, args.frames, &args.out, &args.render.palette)
+        }));
+        match result {
+            Ok(Ok(())) => return,
+            Ok(Err(e)) => eprintln!("{e}"),
+            Err(payload) => {
+                if let Some(dir) = &args.fault_dir {
+                    fault::report(&chip8, dir, fault::panic_message(&*payload));
+                }
+            }
+        }
+        process::exit(1);
+    }
+
+    // the command line wins over whatever was left from last time
+    if let Some(path) = &args.prefs {
+        let prefs = Prefs::load(path);
+        if args.speed.is_none() {
+            args.speed = prefs.instrs_per_second;
+        }
+        if args.volume.is_none() {
+            args.volume = prefs.volume;
+        }
+        if let Some(palette) = prefs.palette()
+            && args.render.palette == RenderConfig::default().palette
+        {
+            args.render.palette = palette;
+        }
+    }
+
+    let hotkeys = match &args.hotkeys {
+        Some(path) => Hotkeys::load(path).unwrap_or_else(|e| {
+            eprintln!("{e}");
+            process::exit(1);
+        }),
+        None => Hotkeys::default(),
+    };
+    let key_map = match &args.keymap {
+        Some(path) => hotkeys::load_key_map(path).unwrap_or_else(|e| {
+            eprintln!("{e}");
+            process::exit(1);
+        }),
+        None => KEY_MAP,
     };
-    let mut chip8 = Chip8::new(config);
 
-    let screen = Arc::clone(&chip8.screen);
-    let timers = Arc::clone(&chip8.timers);
-    let options = Arc::clone(&chip8.shared);
-    let keys = Arc::clone(&chip8.keys);
+    let title = match roms.as_slice() {
+        [rom] => format!("CHIP-8 - {}", rom.name),
+        _ => "CHIP-8".into(),
+    };
+    macroquad::Window::new(&title, emulate(args, roms, hotkeys, key_map));
+}
+
+fn build_chip8(args: &Args, mut roms: Vec<Rom>) -> (Chip8, Handles) {
+    let mut builder = Chip8Builder::new()
+        .config(args.config.clone())
+        .start_address(args.start);
+    if let Some(ips) = args.speed {
+        builder = builder.speed(ips);
+    }
+    if let Some(ipf) = args.ipf {
+        builder = builder.instrs_per_frame(ipf);
+    }
+    if let Some(hz) = args.sound_hz {
+        builder = builder.sound_timer_hz(hz);
+    }
+    if args.pause_at_start {
+        builder = builder.paused();
+    }
+    let builder = match roms.len() {
+        1 => builder.rom(roms.remove(0)),
+        _ => builder.playlist(Playlist::new(roms, args.playlist_slot)),
+    };
+    let (chip8, handles) = builder.build().unwrap_or_else(|e| {
+        eprintln!("{e}");
+        process::exit(1);
+    });
+    let mut chip8 = chip8.with_registers(&args.registers);
+    for &(addr, byte) in &args.pokes {
+        chip8.poke(addr, byte);
+    }
+    chip8.fault_dir = args.fault_dir.clone();
+    if let Some(path) = &args.state {
+        // a file that isn't there yet is made by the first save
+        if path.exists() {
+            match Snapshot::load(path) {
+                Ok(snapshot) => chip8.quick_slot = Some(snapshot),
+                Err(e) => eprintln!("{e}"),
+            }
+        }
+        chip8.state_file = Some(path.clone());
+    }
+    if args.log_events {
+        let events = chip8.subscribe();
+        thread::spawn(move || {
+            for event in events {
+                println!("{event}");
+            }
+        });
+    }
+    (chip8, handles)
+}
 
-    chip8
-        .memory
-        .load_bytes_at(0x200, include_bytes!("../programs/games/snake.ch8"));
+async fn emulate(args: Args, roms: Vec<Rom>, hotkeys: Hotkeys, key_map: [KeyCode; 0x10]) {
+    let (mut chip8, handles) = build_chip8(&args, roms);
+
+    let vblank = Vblank::default();
+    chip8.vblank = Some(Arc::clone(&vblank));
 
     thread::Builder::new()
         .name("compute".into())
         .spawn(move || {
-            chip8.run_at(0x200);
+            let Some(dir) = chip8.fault_dir.clone() else {
+                chip8.run();
+            };
+            // the panic message has already been printed by now, this adds
+            // the state behind it before the thread goes down
+            let Err(payload) = panic::catch_unwind(AssertUnwindSafe(|| chip8.run()));
+            fault::report(&chip8, &dir, fault::panic_message(&*payload));
+            panic::resume_unwind(payload);
         })
         .unwrap();
 
-    start_timer_thread(timers);
+    start_timer_thread(
+        Arc::clone(&handles.timers),
+        Arc::clone(&handles.shared),
+        vblank,
+    );
+    if args.watch {
+        match args.roms.as_slice() {
+            [path] => start_watch_thread(path.clone(), Arc::clone(&handles.shared)),
+            _ => eprintln!("--watch needs exactly one rom to watch"),
+        }
+    }
+
+    let beeper = if args.mute {
+        None
+    } else {
+        Beeper::new(args.volume.unwrap_or(DEFAULT_VOLUME))
+            .await
+            .inspect_err(|e| eprintln!("{e}"))
+            .ok()
+    };
 
-    window_main(screen, options, keys).await;
+    window_main(handles, hotkeys, key_map, args.render, args.prefs, beeper).await;
 }
 
-fn start_timer_thread(timers: Arc<Mutex<Timers>>) {
+// reloads the rom whenever the file changes, keeping the speed and quirks
+fn start_watch_thread(path: PathBuf, shared: Arc<Mutex<Shared>>) {
+    let modified = |path: &PathBuf| fs::metadata(path).and_then(|m| m.modified()).ok();
     thread::spawn(move || {
-        let interval = Duration::from_secs_f64(1.0 / 60.0);
-        let mut next_time = Instant::now() + interval;
+        let mut last = modified(&path);
         loop {
-            {
-                let mut t = timers.lock().unwrap();
-                if t.delay_timer != 0 {
-                    t.delay_timer -= 1;
+            thread::sleep(WATCH_INTERVAL);
+            let now = modified(&path);
+            if now == last {
+                continue;
+            }
+            last = now;
+            // a build halfway through writing it shows up as a failed read or
+            // another change soon after
+            match Rom::load(&path) {
+                Ok(rom) => shared.lock().unwrap().load_request = Some(rom),
+                Err(e) => eprintln!("could not reload {}: {e}", path.display()),
+            }
+        }
+    });
+}
+
+fn start_timer_thread(timers: Arc<Mutex<Timers>>, shared: Arc<Mutex<Shared>>, vblank: Vblank) {
+    thread::spawn(move || {
+        let delay_interval = Duration::from_secs_f64(1.0 / 60.0);
+        let sound_interval = Duration::from_secs_f64(1.0 / timers.lock().unwrap().sound_hz);
+        let mut next_delay = Instant::now() + delay_interval;
+        let mut next_sound = Instant::now() + sound_interval;
+        loop {
+            let next_time = next_delay.min(next_sound);
+            thread::sleep(next_time.saturating_duration_since(Instant::now()));
+
+            // pausing the machine freezes them too, except while it's running
+            // to the next draw, key read or return
+            let (paused, time_scale) = {
+                let shared = shared.lock().unwrap();
+                let stopped = shared.paused && shared.run_until.is_none();
+                (shared.timers_paused || stopped, shared.run_time_scale())
+            };
+            let mut timers = timers.lock().unwrap();
+            if next_delay <= next_time {
+                if !paused {
+                    timers.tick_delay();
                 }
-                if t.sound_timer != 0 {
-                    t.sound_timer -= 1;
+                // frames go on even with the timers paused
+                let (frames, next_frame) = &*vblank;
+                *frames.lock().unwrap() += 1;
+                next_frame.notify_all();
+                next_delay += delay_interval.div_f64(time_scale);
+            }
+            if next_sound <= next_time {
+                if !paused {
+                    timers.tick_sound();
                 }
+                next_sound += sound_interval.div_f64(time_scale);
             }
-
-            thread::sleep(next_time - Instant::now());
-            next_time += interval;
         }
     });
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::Ordering;
+
+    use super::*;
+
+    // `program` loaded at 0x200 with everything else at its default
+    fn load(program: &[u8]) -> Chip8 {
+        load_with(Config::default(), program)
+    }
+
+    fn load_with(config: Config, program: &[u8]) -> Chip8 {
+        let rom = Rom {
+            name: "test".into(),
+            bytes: program.to_vec(),
+            controls: vec![],
+        };
+        let (chip8, _) = Chip8Builder::new().config(config).rom(rom).build().unwrap();
+        chip8
+    }
+
+    fn run(chip8: &mut Chip8, instrs: usize) {
+        for _ in 0..instrs {
+            chip8.execute_instr();
+        }
+    }
+
+    #[test]
+    fn skip_pressed_skips_when_the_key_is_held() {
+        // V0 = 5, skip if key V0 is down
+        let mut chip8 = load(&[0x60, 0x05, 0xE0, 0x9E]);
+        chip8.keys.lock().unwrap().press(5);
+        run(&mut chip8, 2);
+        assert_eq!(chip8.pc, 0x206);
+
+        let mut chip8 = load(&[0x60, 0x05, 0xE0, 0x9E]);
+        chip8.keys.lock().unwrap().press(5);
+        chip8.keys.lock().unwrap().release(5);
+        run(&mut chip8, 2);
+        assert_eq!(chip8.pc, 0x204);
+    }
+
+    #[test]
+    fn flag_writes_are_logged_in_order() {
+        let mut chip8 = load(&[
+            0x60, 0xFF, // V0 = 0xFF
+            0x61, 0x01, // V1 = 1
+            0x80, 0x14, // V0 += V1, carries
+            0x80, 0x15, // V0 -= V1, borrows
+            0x80, 0x06, // V0 >>= 1
+        ]);
+        let events = chip8.subscribe();
+        run(&mut chip8, 5);
+        let writes = events
+            .try_iter()
+            .filter_map(|event| match event {
+                Event::FlagWrite { pc, value } => Some((pc, value)),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(writes, [(0x204, 1), (0x206, 0), (0x208, 1)]);
+    }
+
+    // runs 801`op` with V0, V1 and a VF of 0xAA preset, returns V0 and VF
+    fn alu(v0: u8, v1: u8, op: u8) -> (u8, u8) {
+        alu_with(Config::default(), v0, v1, op)
+    }
+
+    fn alu_with(config: Config, v0: u8, v1: u8, op: u8) -> (u8, u8) {
+        let mut chip8 = load_with(config, &[]);
+        chip8.registers.set(0, v0);
+        chip8.registers.set(1, v1);
+        chip8.registers.set(0xF, 0xAA);
+        chip8.execute(0x80, 0x10 | op);
+        (chip8.registers.get(0), chip8.registers.get(0xF))
+    }
+
+    #[test]
+    fn arithmetic_flags() {
+        assert_eq!(alu(0xF0, 0x0F, 0x1), (0xFF, 0));
+        assert_eq!(alu(0xF0, 0x0F, 0x2), (0x00, 0));
+        assert_eq!(alu(0xF0, 0x0F, 0x3), (0xFF, 0));
+        assert_eq!(alu(0xFF, 0x01, 0x4), (0x00, 1));
+        assert_eq!(alu(0x01, 0x01, 0x4), (0x02, 0));
+        assert_eq!(alu(0x05, 0x03, 0x5), (0x02, 1));
+        assert_eq!(alu(0x03, 0x05, 0x5), (0xFE, 0));
+        assert_eq!(alu(0x03, 0x05, 0x7), (0x02, 1));
+        assert_eq!(alu(0x05, 0x03, 0x7), (0xFE, 0));
+        assert_eq!(alu(0x03, 0x00, 0x6), (0x01, 1));
+        assert_eq!(alu(0x02, 0x00, 0x6), (0x01, 0));
+        assert_eq!(alu(0x81, 0x00, 0xE), (0x02, 1));
+        assert_eq!(alu(0x01, 0x00, 0xE), (0x02, 0));
+    }
+
+    fn shift_config(bitshift_copies_y: bool, shift_vf_from_vx: bool) -> Config {
+        Config {
+            bitshift_copies_y,
+            shift_vf_from_vx,
+            ..Config::default()
+        }
+    }
+
+    #[test]
+    fn shift_flag_source() {
+        // VX and VY differ in the bit shifted out, so VF shows which was used
+        let vf = |copies_y, from_vx| alu_with(shift_config(copies_y, from_vx), 0x02, 0x03, 0x6).1;
+        assert_eq!(vf(false, false), 0);
+        assert_eq!(vf(true, false), 1);
+        assert_eq!(vf(false, true), 0);
+        assert_eq!(vf(true, true), 0);
+    }
+
+    #[test]
+    fn shift_presets() {
+        // the --shift presets, VX = 0x81 and VY = 0x42 so the result and the
+        // bit shifted out both show which register was read
+        let shr = |copies_y, from_vx| alu_with(shift_config(copies_y, from_vx), 0x81, 0x42, 0x6);
+        let shl = |copies_y, from_vx| alu_with(shift_config(copies_y, from_vx), 0x81, 0x42, 0xE);
+        // vx
+        assert_eq!(shr(false, false), (0x40, 1));
+        assert_eq!(shl(false, false), (0x02, 1));
+        // vy
+        assert_eq!(shr(true, false), (0x21, 0));
+        assert_eq!(shl(true, false), (0x84, 0));
+        // vy-flag-vx
+        assert_eq!(shr(true, true), (0x21, 1));
+        assert_eq!(shl(true, true), (0x84, 1));
+    }
+
+    #[test]
+    fn flag_wins_over_the_result_in_vf() {
+        let mut chip8 = load(&[]);
+        chip8.registers.set(0xF, 0xFF);
+        chip8.registers.set(1, 0x01);
+        // VF += V1 carries, so VF ends up as the carry rather than the sum
+        chip8.execute(0x8F, 0x14);
+        assert_eq!(chip8.registers.get(0xF), 1);
+    }
+
+    #[test]
+    fn draw_flags_collisions() {
+        // I = the 0 glyph, drawn twice over itself
+        let mut chip8 = load(&[0xA0, 0x50, 0xD0, 0x05, 0xD0, 0x05]);
+        run(&mut chip8, 2);
+        assert_eq!(chip8.registers.get(0xF), 0);
+        run(&mut chip8, 1);
+        assert_eq!(chip8.registers.get(0xF), 1);
+    }
+
+    #[test]
+    fn clear_only_touches_the_selected_planes() {
+        let mut chip8 = load(&[
+            0xF3, 0x01, // both planes
+            0xA0, 0x50, // I = the 0 glyph, the 1 glyph after it goes in plane 2
+            0xD0, 0x05, // draw at (0, 0)
+            0xF1, 0x01, // first plane only
+            0x00, 0xE0, // clear
+        ]);
+        // the top rows of 0 and 1 both light column 2
+        run(&mut chip8, 3);
+        assert_eq!(chip8.screen.lock().unwrap().pixel(2, 0), 0b11);
+        run(&mut chip8, 2);
+        assert_eq!(chip8.screen.lock().unwrap().pixel(2, 0), 0b10);
+
+        // classic programs never select a plane, so the clear is total
+        let mut chip8 = load(&[0xA0, 0x50, 0xD0, 0x05, 0x00, 0xE0]);
+        run(&mut chip8, 3);
+        assert_eq!(*chip8.screen.lock().unwrap(), Screen::new());
+    }
+
+    #[test]
+    fn sound_timer_counts_at_its_own_rate() {
+        let mut chip8 = load(&[
+            0x60, 0x64, // V0 = 100
+            0xF0, 0x15, // delay = V0
+            0xF0, 0x18, // sound = V0
+            0x12, 0x06, // spin
+        ]);
+        chip8.timers.lock().unwrap().sound_hz = 120.0;
+        chip8.run_frames(30);
+        let timers = chip8.timers.lock().unwrap();
+        assert_eq!(chip8.delay_timer.load(Ordering::Relaxed), 70);
+        assert_eq!(timers.sound_timer, 40);
+    }
+
+    #[test]
+    fn timers_run_out_after_a_second() {
+        let mut chip8 = load(&[
+            0x60, 0x3C, // V0 = 60
+            0xF0, 0x15, // delay = V0
+            0xF0, 0x18, // sound = V0
+            0x12, 0x06, // spin
+        ]);
+        let timers = |chip8: &Chip8| {
+            let delay = chip8.delay_timer.load(Ordering::Relaxed);
+            (delay, chip8.timers.lock().unwrap().sound_timer)
+        };
+        chip8.run_frames(59);
+        assert_eq!(timers(&chip8), (1, 1));
+        chip8.run_frames(1);
+        assert_eq!(timers(&chip8), (0, 0));
+        // and they stay there
+        chip8.run_frames(1);
+        assert_eq!(timers(&chip8), (0, 0));
+    }
+
+    #[test]
+    fn polled_delay_timer_only_counts_down() {
+        let mut chip8 = load(&[0x60, 0xFF, 0xF0, 0x15]);
+        run(&mut chip8, 2);
+        let timers = Arc::clone(&chip8.timers);
+        let ticker = thread::spawn(move || {
+            // more ticks than it takes, the extra ones must leave it at 0
+            for _ in 0..300 {
+                timers.lock().unwrap().tick_delay();
+                thread::yield_now();
+            }
+        });
+        let mut last = 0xFF;
+        loop {
+            let done = ticker.is_finished();
+            chip8.execute(0xF0, 0x07);
+            let now = chip8.registers.get(0);
+            assert!(now <= last, "went from {last} up to {now}");
+            last = now;
+            if done {
+                break;
+            }
+        }
+        ticker.join().unwrap();
+        assert_eq!(last, 0);
+    }
+
+    // draws the 0 glyph, whose top row is 4 pixels wide, with VX and VY
+    fn draw_glyph_at(config: Config, x: u8, y: u8) -> Screen {
+        let mut chip8 = load_with(config, &[0x60, x, 0x61, y, 0xA0, 0x50, 0xD0, 0x15]);
+        run(&mut chip8, 4);
+        chip8.screen.lock().unwrap().clone()
+    }
+
+    #[test]
+    fn draw_clips_at_the_right_edge() {
+        let screen = draw_glyph_at(Config::default(), 63, 0);
+        assert_eq!(screen.pixel(63, 0), 1);
+        assert_eq!((0..3).map(|x| screen.pixel(x, 0)).sum::<u8>(), 0);
+    }
+
+    #[test]
+    fn draw_wraps_the_starting_position() {
+        let screen = draw_glyph_at(Config::default(), 70, 35);
+        assert_eq!(screen, draw_glyph_at(Config::default(), 6, 3));
+        assert_eq!(screen.pixel(6, 3), 1);
+        assert_eq!(screen.pixel(5, 3), 0);
+    }
+
+    #[test]
+    fn add_index_flags_overflow() {
+        let mut chip8 = load(&[]);
+        chip8.i = 0xFFF;
+        chip8.registers.set(0, 1);
+        chip8.execute(0xF0, 0x1E);
+        assert_eq!((chip8.i, chip8.registers.get(0xF)), (0x000, 1));
+    }
+
+    #[test]
+    fn add_index_wraps_at_the_memory_size() {
+        let config = Config {
+            memory_size: MemorySize::Extended,
+            ..Config::default()
+        };
+        let mut chip8 = load_with(config, &[]);
+        chip8.i = 0xFFF;
+        chip8.registers.set(0, 1);
+        chip8.execute(0xF0, 0x1E);
+        assert_eq!((chip8.i, chip8.registers.get(0xF)), (0x1000, 0));
+
+        chip8.i = 0xFFFF;
+        chip8.execute(0xF0, 0x1E);
+        assert_eq!((chip8.i, chip8.registers.get(0xF)), (0x0000, 1));
+    }
+
+    #[test]
+    fn random_init_is_reproducible() {
+        let seeded = |seed| Config {
+            random_init_seed: Some(seed),
+            ..Config::default()
+        };
+        assert_eq!(power_on_state(&seeded(1)), power_on_state(&seeded(1)));
+        assert_ne!(power_on_state(&seeded(1)), power_on_state(&seeded(2)));
+
+        let (memory, registers) = power_on_state(&Config::default());
+        assert_eq!(memory, Memory::new(MemorySize::Standard));
+        assert_eq!(registers, Registers::new());
+    }
+
+    #[test]
+    #[should_panic(expected = "unknown arithmetic instruction")]
+    fn undefined_arithmetic_opcodes_are_named() {
+        run(&mut load(&[0x80, 0x18]), 1);
+    }
+
+    #[test]
+    fn lenient_mode_skips_undefined_arithmetic_opcodes() {
+        let config = Config {
+            lenient_opcodes: true,
+            ..Config::default()
+        };
+        let mut chip8 = load_with(config, &[0x80, 0x18]);
+        let events = chip8.subscribe();
+        run(&mut chip8, 1);
+        assert_eq!((chip8.pc, chip8.skipped_instrs), (0x202, 1));
+        let fault = Event::Fault {
+            pc: 0x200,
+            instr: [0x80, 0x18],
+        };
+        assert_eq!(events.try_recv(), Ok(fault));
+    }
+
+    #[test]
+    #[should_panic(expected = "0x0123 at 0x200 calls machine code at 0x123")]
+    fn machine_code_calls_are_named() {
+        run(&mut load(&[0x01, 0x23]), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "calls machine code at 0x000")]
+    fn an_all_zero_instruction_is_a_machine_code_call() {
+        run(&mut load(&[0x00, 0x00]), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "unknown instruction 0xFFFF")]
+    fn unknown_opcodes_are_named() {
+        run(&mut load(&[0xFF, 0xFF]), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "there is no 8XYF operation")]
+    fn the_last_arithmetic_nibble_is_undefined() {
+        run(&mut load(&[0x80, 0x1F]), 1);
+    }
+
+    #[test]
+    fn lenient_mode_skips_machine_code_and_unknown_opcodes() {
+        let config = Config {
+            lenient_opcodes: true,
+            ..Config::default()
+        };
+        // 0NNN, then 5XY1 and FXFF, which no variant defines
+        let mut chip8 = load_with(config, &[0x01, 0x23, 0x51, 0x21, 0xF0, 0xFF]);
+        run(&mut chip8, 3);
+        assert_eq!((chip8.pc, chip8.skipped_instrs), (0x206, 3));
+    }
+
+    #[test]
+    fn opcodes_next_to_undefined_ones_still_run() {
+        // 00E0 clears the screen where 00E1 would be machine code, and 8XYE
+        // shifts where 8XYF is undefined
+        let mut chip8 = load(&[0x60, 0x81, 0x80, 0x0E, 0x00, 0xE0]);
+        chip8.screen.lock().unwrap().toggle(0, 0, 0);
+        run(&mut chip8, 3);
+        assert_eq!(chip8.registers.get(0), 0x02);
+        assert_eq!(chip8.registers.get(0xF), 1);
+        assert_eq!(chip8.screen.lock().unwrap().pixel(0, 0), 0);
+    }
+
+    #[test]
+    fn bcd_wraps_past_the_end_of_memory() {
+        let mut chip8 = load(&[]);
+        chip8.i = 0xFFE;
+        chip8.registers.set(0, 123);
+        chip8.execute(0xF0, 0x33);
+        let digits = [0xFFE, 0xFFF, 0x000].map(|i| chip8.memory.get(i));
+        assert_eq!(digits, [1, 2, 3]);
+    }
+
+    #[test]
+    #[should_panic(expected = "would write past the end of memory")]
+    fn strict_bcd_past_the_end_of_memory_is_an_error() {
+        let config = Config {
+            strict_memory_bounds: true,
+            ..Config::default()
+        };
+        let mut chip8 = load_with(config, &[]);
+        chip8.i = 0xFFE;
+        chip8.execute(0xF0, 0x33);
+    }
+
+    #[test]
+    fn config_round_trips_through_toml() {
+        let config = Config {
+            bitshift_copies_y: true,
+            memory_size: MemorySize::Extended,
+            wait_key_policy: WaitKeyPolicy::Newest,
+            rng_seed: Some(7),
+            rewind_frames: 120,
+            ..Config::default()
+        };
+        let text = toml::to_string(&config).unwrap();
+        assert_eq!(toml::from_str::<Config>(&text).unwrap(), config);
+    }
+
+    #[test]
+    fn key_index_past_0xf_uses_the_low_nibble() {
+        let mut chip8 = load(&[0x60, 0x1A, 0xE0, 0x9E]);
+        chip8.keys.lock().unwrap().press(0xA);
+        run(&mut chip8, 2);
+        assert_eq!(chip8.pc, 0x206);
+    }
+
+    #[test]
+    #[should_panic(expected = "past the last key 0xF")]
+    fn strict_key_index_past_0xf_is_an_error() {
+        let config = Config {
+            strict_key_index: true,
+            ..Config::default()
+        };
+        run(&mut load_with(config, &[0x60, 0x1A, 0xE0, 0x9E]), 2);
+    }
+
+    #[test]
+    fn jump_with_offset_wraps_past_the_end_of_memory() {
+        let mut chip8 = load(&[]);
+        chip8.registers.set(0, 2);
+        chip8.execute(0xBF, 0xFF);
+        assert_eq!(chip8.pc, 0x001);
+
+        // BXNN adds VX instead, here VF
+        let config = Config {
+            jump_with_offset_register: true,
+            ..Config::default()
+        };
+        let mut chip8 = load_with(config, &[]);
+        chip8.registers.set(0xF, 3);
+        chip8.execute(0xBF, 0xFF);
+        assert_eq!(chip8.pc, 0x002);
+    }
+
+    #[test]
+    #[should_panic(expected = "jumps past the end of memory")]
+    fn strict_jump_with_offset_past_the_end_of_memory_is_an_error() {
+        let config = Config {
+            strict_memory_bounds: true,
+            ..Config::default()
+        };
+        let mut chip8 = load_with(config, &[]);
+        chip8.registers.set(0, 2);
+        chip8.execute(0xBF, 0xFF);
+    }
+
+    // runs FX0A with keys 3 and 7 down, 3 already down the last time FX0A
+    // looked, then lets both go. the key it stored, if it stopped waiting
+    fn wait_key_with(wait_key_policy: WaitKeyPolicy) -> Option<u8> {
+        let config = Config {
+            wait_key_policy,
+            ..Config::default()
+        };
+        let mut chip8 = load_with(config, &[0xF0, 0x0A]);
+        chip8.keys_at_last_wait = 1 << 3;
+        chip8.keys.lock().unwrap().set(1 << 3 | 1 << 7);
+        run(&mut chip8, 1);
+        chip8.keys.lock().unwrap().set(0);
+        run(&mut chip8, 1);
+        (chip8.pc == 0x202).then(|| chip8.registers.get(0))
+    }
+
+    #[test]
+    fn wait_key_stays_put_at_the_edges_of_memory() {
+        // run directly at pc 0, where backing up 2 would underflow
+        let mut chip8 = load(&[]);
+        chip8.pc = 0;
+        chip8.execute(0xF0, 0x0A);
+        assert_eq!(chip8.pc, 0);
+        // fetched from the last word, so pc has already wrapped to 0
+        chip8.pc = 0xFFE;
+        chip8.memory.set(0xFFE, 0xF0);
+        chip8.memory.set(0xFFF, 0x0A);
+        run(&mut chip8, 1);
+        assert_eq!(chip8.pc, 0xFFE);
+    }
+
+    #[test]
+    fn wait_key_policies() {
+        assert_eq!(wait_key_with(WaitKeyPolicy::Lowest), Some(3));
+        assert_eq!(wait_key_with(WaitKeyPolicy::Newest), Some(7));
+        assert_eq!(wait_key_with(WaitKeyPolicy::OnlyOne), None);
+    }
+
+    #[test]
+    fn bcd_writes_the_hundreds_first() {
+        let mut chip8 = load(&[]);
+        chip8.i = 0x300;
+        chip8.registers.set(0, 234);
+        chip8.execute(0xF0, 0x33);
+        let digits = [0x300, 0x301, 0x302].map(|i| chip8.memory.get(i));
+        assert_eq!(digits, [2, 3, 4]);
+    }
+
+    #[test]
+    fn sprite_top_bit_is_the_leftmost_pixel() {
+        // one row of 0b1100_0000 at (0, 0)
+        let mut chip8 = load(&[0xA2, 0x04, 0xD0, 0x01, 0xC0]);
+        run(&mut chip8, 2);
+        let screen = chip8.screen.lock().unwrap();
+        let row = (0..8).map(|x| screen.pixel(x, 0)).collect::<Vec<_>>();
+        assert_eq!(row, [1, 1, 0, 0, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn seeded_random_is_reproducible() {
+        // V0 to V3 = random bytes
+        let program = [0xC0, 0xFF, 0xC1, 0xFF, 0xC2, 0xFF, 0xC3, 0xFF];
+        let registers = |seed| {
+            let config = Config {
+                rng_seed: Some(seed),
+                ..Config::default()
+            };
+            let mut chip8 = load_with(config, &program);
+            run(&mut chip8, 4);
+            chip8.registers
+        };
+        assert_eq!(registers(42), registers(42));
+        assert_ne!(registers(42), registers(43));
+    }
+
+    #[test]
+    fn sprites_straddling_the_right_edge_clip_or_wrap() {
+        // the top row covers columns 62 and 63, then 64 and 65 off the edge
+        let clipped = draw_glyph_at(Config::default(), 62, 0);
+        let row = |screen: &Screen| [62, 63, 0, 1].map(|x| screen.pixel(x, 0));
+        assert_eq!(row(&clipped), [1, 1, 0, 0]);
+
+        let config = Config {
+            sprite_wrapping: true,
+            ..Config::default()
+        };
+        let wrapped = draw_glyph_at(config, 62, 0);
+        assert_eq!(row(&wrapped), [1, 1, 1, 1]);
+    }
+
+    #[test]
+    fn display_wait_draws_once_per_frame() {
+        const DRAWS: usize = 10;
+        let config = Config {
+            display_wait: true,
+            ..Config::default()
+        };
+        // draw, jump back to the draw
+        let mut chip8 = load_with(config, &[0xD0, 0x11, 0x12, 0x00]);
+        // the test ticks the frames instead of the timer thread
+        let vblank = Vblank::default();
+        chip8.vblank = Some(Arc::clone(&vblank));
+
+        // the frame each draw finished in. instructions run back to back
+        // here, faster than any instrs_per_second, so without the wait
+        // several draws would land in the same frame
+        let counter = Arc::clone(&vblank);
+        let compute = thread::spawn(move || {
+            (0..DRAWS)
+                .map(|_| {
+                    chip8.execute_instr();
+                    let frame = *counter.0.lock().unwrap();
+                    chip8.execute_instr();
+                    frame
+                })
+                .collect::<Vec<_>>()
+        });
+        while !compute.is_finished() {
+            *vblank.0.lock().unwrap() += 1;
+            vblank.1.notify_all();
+            thread::sleep(Duration::from_millis(1));
+        }
+
+        let frames = compute.join().unwrap();
+        assert!(
+            frames.windows(2).all(|pair| pair[0] < pair[1]),
+            "{frames:?}"
+        );
+    }
+
+    #[test]
+    fn snapshot_diff_lists_only_what_changed() {
+        let mut chip8 = load(&[]);
+        let before = chip8.snapshot();
+        chip8.registers.set(3, 0x20);
+        chip8.memory.set(0x300, 0xFF);
+        chip8.screen.lock().unwrap().toggle(0, 1, 5);
+        let diff = before.diff(&chip8.snapshot());
+        assert_eq!(
+            diff,
+            [
+                "V3: 0x00 -> 0x20".to_string(),
+                "[0x300]: 0x00 -> 0xFF".to_string(),
+                format!("plane 0 row 5: {} -> .#{}", ".".repeat(64), ".".repeat(62)),
+            ]
+        );
+        assert!(before.diff(&before).is_empty());
+    }
+
+    // random roms from random power-on states, which lenient mode should run
+    // without ever panicking. CHIP8_FUZZ_ROMS sets how many to try, so
+    // `CHIP8_FUZZ_ROMS=100000 cargo test --release random_roms` searches
+    // harder than the default. a failure names the seed to reproduce it with
+    #[test]
+    fn random_roms_never_panic_in_lenient_mode() {
+        let roms = std::env::var("CHIP8_FUZZ_ROMS")
+            .ok()
+            .and_then(|n| n.parse().ok())
+            .unwrap_or(100);
+        for seed in 0..roms {
+            let mut rng = ChaCha12Rng::seed_from_u64(seed);
+            let len = rng.random_range(2..=256);
+            let program = (0..len).map(|_| rng.random()).collect::<Vec<u8>>();
+            let config = Config {
+                lenient_opcodes: true,
+                random_init_seed: Some(seed),
+                rng_seed: Some(seed),
+                ..Config::default()
+            };
+            let mut chip8 = load_with(config, &program);
+            let result = panic::catch_unwind(AssertUnwindSafe(|| {
+                chip8.run_headless(PROGRAM_START, 5_000);
+            }));
+            assert!(result.is_ok(), "seed {seed} panicked");
+        }
+    }
+
+    #[test]
+    fn lenient_faults_write_a_report_and_a_state_once_per_address() {
+        let config = Config {
+            lenient_opcodes: true,
+            ..Config::default()
+        };
+        // an unknown opcode, then back to it
+        let mut chip8 = load_with(config, &[0x80, 0x18, 0x12, 0x00]);
+        let dir = std::env::temp_dir().join(format!("chip-8-faults-{}", std::process::id()));
+        chip8.fault_dir = Some(dir.clone());
+        run(&mut chip8, 4);
+
+        let mut files = fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect::<Vec<_>>();
+        files.sort();
+        let state = Snapshot::load(&files[0]);
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[0].extension().unwrap(), "toml");
+        assert_eq!(files[1].extension().unwrap(), "txt");
+        // taken just after the skip, so it goes back to right after it
+        let mut restored = load(&[]);
+        restored.restore(state.unwrap());
+        assert_eq!((restored.pc, restored.memory.get(0x201)), (0x202, 0x18));
+    }
+
+    // a splash screen: delay = 60, then loop until it reads 0
+    const SPLASH: [u8; 12] = [
+        0x60, 0x3C, // V0 = 60
+        0xF0, 0x15, // delay = V0
+        0xF1, 0x07, // V1 = delay
+        0x31, 0x00, // skip if V1 == 0
+        0x12, 0x04, // back to the read
+        0x12, 0x0A, // spin
+    ];
+
+    fn fast_booting(chip8: &Chip8) -> bool {
+        chip8.shared.lock().unwrap().fast_booting
+    }
+
+    #[test]
+    fn fast_boot_speeds_up_a_splash_wait_without_touching_the_timer() {
+        let config = Config {
+            fast_boot: true,
+            ..Config::default()
+        };
+        let mut chip8 = load_with(config, &SPLASH);
+        // one read isn't a loop yet
+        run(&mut chip8, 3);
+        assert!(!fast_booting(&chip8));
+        // the second from the same FX07 is
+        run(&mut chip8, 3);
+        assert!(fast_booting(&chip8));
+        assert_eq!(chip8.delay_timer.load(Ordering::Relaxed), 60);
+        assert!(chip8.shared.lock().unwrap().run_time_scale() > 1.0);
+
+        // back to normal once the read sees 0
+        chip8.delay_timer.store(0, Ordering::Relaxed);
+        run(&mut chip8, 3);
+        assert!(!fast_booting(&chip8));
+        assert_eq!(chip8.shared.lock().unwrap().run_time_scale(), 1.0);
+    }
+
+    #[test]
+    fn fast_boot_leaves_waits_alone_once_keys_are_read() {
+        let config = Config {
+            fast_boot: true,
+            ..Config::default()
+        };
+        let mut chip8 = load_with(config, &SPLASH);
+        chip8.read_keys = true;
+        run(&mut chip8, 6);
+        assert!(!fast_booting(&chip8));
+
+        let mut chip8 = load(&SPLASH);
+        run(&mut chip8, 6);
+        assert!(!fast_booting(&chip8));
+    }
+}
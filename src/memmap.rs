@@ -0,0 +1,116 @@
+use std::fmt::Write;
+
+use crate::{
+    Chip8,
+    instr::Instr,
+    memory::{FONT_DATA, FONT_START},
+};
+
+const ROW: usize = 16;
+
+// what a row of memory is used for
+fn region(chip8: &Chip8, addr: usize) -> &'static str {
+    let program = chip8.program_start..chip8.program_start + chip8.rom_len;
+    if (FONT_START..FONT_START + FONT_DATA.len()).contains(&addr) {
+        "font"
+    } else if addr < chip8.program_start {
+        "reserved"
+    } else if program.contains(&addr) {
+        "program"
+    } else {
+        "free"
+    }
+}
+
+// `label=addr`, with the instruction there when that's a real one in the
+// program
+fn marker(chip8: &Chip8, label: &str, addr: usize) -> String {
+    if !addr.is_multiple_of(2) || region(chip8, addr) != "program" {
+        return format!("{label}={addr:#05X}");
+    }
+    let instr = Instr::new(chip8.memory.get(addr), chip8.memory.get(addr + 1));
+    format!(
+        "{label}={addr:#05X} ({})",
+        instr.disassemble_with(&chip8.config)
+    )
+}
+
+// A hex dump of memory split into labeled regions, with the pc, I and the
+// return addresses on the stack marked. `cursor` is decoded like the pc is,
+// and is usually the pc. Runs of zero rows are collapsed.
+pub fn memory_map(chip8: &Chip8, cursor: usize) -> String {
+    let mut out = String::new();
+    let mut current = "";
+    let mut skipping = false;
+    for start in (0..chip8.memory.len()).step_by(ROW) {
+        let row = start..start + ROW;
+        let bytes = row
+            .clone()
+            .map(|addr| chip8.memory.get(addr))
+            .collect::<Vec<_>>();
+
+        let mut markers = vec![];
+        if row.contains(&chip8.pc) {
+            markers.push(marker(chip8, "pc", chip8.pc));
+        }
+        if row.contains(&cursor) && cursor != chip8.pc {
+            markers.push(marker(chip8, "cursor", cursor));
+        }
+        if row.contains(&chip8.i) {
+            markers.push(format!("I={:#05X}", chip8.i));
+        }
+        for (depth, ret) in chip8.stack.iter().enumerate() {
+            if row.contains(ret) {
+                markers.push(format!("return #{depth}={ret:#05X}"));
+            }
+        }
+
+        let label = region(chip8, start);
+        if label != current {
+            writeln!(out, "; {label}").unwrap();
+            current = label;
+            skipping = false;
+        }
+        if markers.is_empty() && bytes.iter().all(|b| *b == 0) {
+            if !skipping {
+                writeln!(out, "    ...").unwrap();
+                skipping = true;
+            }
+            continue;
+        }
+        skipping = false;
+
+        write!(out, "    {start:#06X} ").unwrap();
+        for byte in bytes {
+            write!(out, " {byte:02X}").unwrap();
+        }
+        if !markers.is_empty() {
+            write!(out, "  <- {}", markers.join(", ")).unwrap();
+        }
+        writeln!(out).unwrap();
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{builder::Chip8Builder, playlist::Rom};
+
+    #[test]
+    fn the_cursor_is_decoded_like_the_pc() {
+        let (chip8, _) = Chip8Builder::new()
+            .rom(Rom {
+                name: "test".into(),
+                bytes: vec![0x00, 0xE0, 0xB3, 0x45],
+                controls: vec![],
+            })
+            .build()
+            .unwrap();
+        let map = memory_map(&chip8, 0x202);
+        assert!(map.contains("pc=0x200 (CLS), cursor=0x202 (JP V0, 0x345)"));
+        // odd addresses are mid instruction, so there's nothing to decode
+        assert!(memory_map(&chip8, 0x203).contains("cursor=0x203\n"));
+        assert!(!memory_map(&chip8, 0x200).contains("cursor"));
+    }
+}
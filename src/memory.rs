@@ -1,32 +1,80 @@
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
-pub struct Memory([u8; 4096]);
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Hash, Serialize, Deserialize)]
+pub enum MemorySize {
+    // 4 KB, 12-bit addresses
+    #[default]
+    Standard,
+    // 64 KB, 16-bit addresses (XO-CHIP)
+    Extended,
+}
+
+impl MemorySize {
+    pub fn bytes(self) -> usize {
+        match self {
+            MemorySize::Standard => 0x1000,
+            MemorySize::Extended => 0x10000,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Memory(Vec<u8>);
 
 impl Memory {
-    pub fn new() -> Self {
-        let mut mem = Memory([0; 4096]);
+    pub fn new(size: MemorySize) -> Self {
+        let mut mem = Memory(vec![0; size.bytes()]);
         mem.set_font();
         mem
     }
 
+    // random bytes everywhere but the font
+    pub fn randomize(&mut self, rng: &mut impl Rng) {
+        rng.fill(&mut self.0[..]);
+        self.set_font();
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    // masks an address to the memory's address width, the size is always a
+    // power of two so this is the same as wrapping around
+    pub fn wrap(&self, i: usize) -> usize {
+        i & (self.0.len() - 1)
+    }
+
     pub fn get(&self, i: usize) -> u8 {
-        self.0[i]
+        self.0[self.wrap(i)]
     }
 
     pub fn set(&mut self, i: usize, x: u8) {
+        let i = self.wrap(i);
         self.0[i] = x;
     }
 
-    pub fn load_bytes_at(&mut self, i: usize, data: &[u8]) {
+    // unlike set this doesn't wrap, data that runs off the end is an error
+    pub fn load_bytes_at(&mut self, i: usize, data: &[u8]) -> Result<(), String> {
+        let room = self.0.len().saturating_sub(i);
+        if data.len() > room {
+            return Err(format!(
+                "{} bytes don't fit at {i:#05X}, only {room} do",
+                data.len()
+            ));
+        }
         self.0[i..i + data.len()].clone_from_slice(data);
+        Ok(())
     }
 
-    fn set_font(&mut self) {
-        // apparently its common to put the font data here
-        self.load_bytes_at(0x50, &FONT_DATA)
+    pub fn set_font(&mut self) {
+        self.0[FONT_START..FONT_START + FONT_DATA.len()].clone_from_slice(&FONT_DATA);
     }
 }
 
-const FONT_DATA: [u8; 80] = [
+// apparently its common to put the font data here
+pub const FONT_START: usize = 0x50;
+pub const FONT_DATA: [u8; 80] = [
     0xF0, 0x90, 0x90, 0x90, 0xF0, // 0
     0x20, 0x60, 0x20, 0x20, 0x70, // 1
     0xF0, 0x10, 0xF0, 0x80, 0xF0, // 2
@@ -44,3 +92,76 @@ const FONT_DATA: [u8; 80] = [
     0xF0, 0x80, 0xF0, 0x80, 0xF0, // E
     0xF0, 0x80, 0xF0, 0x80, 0x80, // F
 ];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Chip8, Config, builder::Chip8Builder};
+
+    // `instr` at `pc` on an empty machine of the given size, run once
+    fn run_at_end(memory_size: MemorySize, pc: usize, instr: [u8; 2]) -> Chip8 {
+        let config = Config {
+            memory_size,
+            ..Config::default()
+        };
+        let (mut chip8, _) = Chip8Builder::new().config(config).build().unwrap();
+        chip8.memory.set(pc, instr[0]);
+        chip8.memory.set(pc + 1, instr[1]);
+        chip8.pc = pc;
+        chip8.execute_instr();
+        chip8
+    }
+
+    #[test]
+    fn standard_memory_wraps_at_4k() {
+        let mut memory = Memory::new(MemorySize::Standard);
+        assert_eq!(memory.wrap(0x1000), 0x000);
+        assert_eq!(memory.wrap(0x1234), 0x234);
+        memory.set(0x1300, 0xAB);
+        assert_eq!(memory.get(0x300), 0xAB);
+    }
+
+    #[test]
+    fn extended_memory_wraps_at_64k() {
+        let mut memory = Memory::new(MemorySize::Extended);
+        assert_eq!(memory.wrap(0x1000), 0x1000);
+        assert_eq!(memory.wrap(0x10000), 0x0000);
+        memory.set(0x10300, 0xAB);
+        assert_eq!(memory.get(0x300), 0xAB);
+        assert_eq!(memory.get(0x1300), 0x00);
+    }
+
+    #[test]
+    fn pc_wraps_at_4k() {
+        // V0 = 1, skip if V0 == 0 (it starts at 0) and call 0x300, each right
+        // before the end of memory
+        assert_eq!(
+            run_at_end(MemorySize::Standard, 0xFFE, [0x60, 0x01]).pc,
+            0x000
+        );
+        assert_eq!(
+            run_at_end(MemorySize::Standard, 0xFFC, [0x30, 0x00]).pc,
+            0x000
+        );
+        let chip8 = run_at_end(MemorySize::Standard, 0xFFE, [0x23, 0x00]);
+        assert_eq!((chip8.pc, chip8.stack.as_slice()), (0x300, &[0x000][..]));
+    }
+
+    #[test]
+    fn pc_wraps_at_64k() {
+        assert_eq!(
+            run_at_end(MemorySize::Extended, 0xFFE, [0x60, 0x01]).pc,
+            0x1000
+        );
+        assert_eq!(
+            run_at_end(MemorySize::Extended, 0xFFFE, [0x60, 0x01]).pc,
+            0x0000
+        );
+        assert_eq!(
+            run_at_end(MemorySize::Extended, 0xFFFC, [0x30, 0x00]).pc,
+            0x0000
+        );
+        let chip8 = run_at_end(MemorySize::Extended, 0xFFFE, [0x23, 0x00]);
+        assert_eq!((chip8.pc, chip8.stack.as_slice()), (0x300, &[0x0000][..]));
+    }
+}
@@ -0,0 +1,269 @@
+use std::fmt;
+
+use crate::{Config, instr::Instr};
+
+// a decoded instruction, named after what it does rather than its bits
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Opcode {
+    ClearScreen,
+    Return,
+    // 0NNN, machine code for the host computer
+    Sys(u16),
+    Jump(u16),
+    Call(u16),
+    SkipEqImmediate { x: u8, value: u8 },
+    SkipNeImmediate { x: u8, value: u8 },
+    SkipEqRegister { x: u8, y: u8 },
+    SetImmediate { x: u8, value: u8 },
+    AddImmediate { x: u8, value: u8 },
+    Copy { x: u8, y: u8 },
+    Or { x: u8, y: u8 },
+    And { x: u8, y: u8 },
+    Xor { x: u8, y: u8 },
+    Add { x: u8, y: u8 },
+    Sub { x: u8, y: u8 },
+    ShiftRight { x: u8, y: u8 },
+    SubFrom { x: u8, y: u8 },
+    ShiftLeft { x: u8, y: u8 },
+    SkipNeRegister { x: u8, y: u8 },
+    SetIndex(u16),
+    // x is only used with the jump_with_offset_register quirk
+    JumpWithOffset { x: u8, address: u16 },
+    Random { x: u8, mask: u8 },
+    Display { x: u8, y: u8, n: u8 },
+    SkipPressed { x: u8 },
+    SkipNotPressed { x: u8 },
+    // FN01 (XO-CHIP)
+    SelectPlanes(u8),
+    ReadDelay { x: u8 },
+    WaitKey { x: u8 },
+    SetDelay { x: u8 },
+    SetSound { x: u8 },
+    AddIndex { x: u8 },
+    FontCharacter { x: u8 },
+    Bcd { x: u8 },
+    Store { x: u8 },
+    Load { x: u8 },
+}
+
+impl Opcode {
+    // spec-style names of every opcode, in the order `index` numbers them
+    pub const PATTERNS: [&'static str; 36] = [
+        "00E0", "00EE", "0NNN", "1NNN", "2NNN", "3XNN", "4XNN", "5XY0", "6XNN", "7XNN", "8XY0",
+        "8XY1", "8XY2", "8XY3", "8XY4", "8XY5", "8XY6", "8XY7", "8XYE", "9XY0", "ANNN", "BNNN",
+        "CXNN", "DXYN", "EX9E", "EXA1", "FN01", "FX07", "FX0A", "FX15", "FX18", "FX1E", "FX29",
+        "FX33", "FX55", "FX65",
+    ];
+
+    // where this opcode's pattern is in PATTERNS
+    pub fn index(self) -> usize {
+        match self {
+            Opcode::ClearScreen => 0,
+            Opcode::Return => 1,
+            Opcode::Sys(_) => 2,
+            Opcode::Jump(_) => 3,
+            Opcode::Call(_) => 4,
+            Opcode::SkipEqImmediate { .. } => 5,
+            Opcode::SkipNeImmediate { .. } => 6,
+            Opcode::SkipEqRegister { .. } => 7,
+            Opcode::SetImmediate { .. } => 8,
+            Opcode::AddImmediate { .. } => 9,
+            Opcode::Copy { .. } => 10,
+            Opcode::Or { .. } => 11,
+            Opcode::And { .. } => 12,
+            Opcode::Xor { .. } => 13,
+            Opcode::Add { .. } => 14,
+            Opcode::Sub { .. } => 15,
+            Opcode::ShiftRight { .. } => 16,
+            Opcode::SubFrom { .. } => 17,
+            Opcode::ShiftLeft { .. } => 18,
+            Opcode::SkipNeRegister { .. } => 19,
+            Opcode::SetIndex(_) => 20,
+            Opcode::JumpWithOffset { .. } => 21,
+            Opcode::Random { .. } => 22,
+            Opcode::Display { .. } => 23,
+            Opcode::SkipPressed { .. } => 24,
+            Opcode::SkipNotPressed { .. } => 25,
+            Opcode::SelectPlanes(_) => 26,
+            Opcode::ReadDelay { .. } => 27,
+            Opcode::WaitKey { .. } => 28,
+            Opcode::SetDelay { .. } => 29,
+            Opcode::SetSound { .. } => 30,
+            Opcode::AddIndex { .. } => 31,
+            Opcode::FontCharacter { .. } => 32,
+            Opcode::Bcd { .. } => 33,
+            Opcode::Store { .. } => 34,
+            Opcode::Load { .. } => 35,
+        }
+    }
+
+    // like to_string, but with BNNN naming the register it really adds when
+    // the jump_with_offset_register quirk makes it BXNN
+    pub fn mnemonic(self, config: &Config) -> String {
+        match self {
+            Opcode::JumpWithOffset { x, address } if config.jump_with_offset_register => {
+                format!("JP V{x:X}, {address:#05X}")
+            }
+            _ => self.to_string(),
+        }
+    }
+
+    // None for anything no supported variant defines
+    pub fn decode(instr: &Instr) -> Option<Opcode> {
+        let address = instr.as_address() as u16;
+        let value = instr.as_u8();
+        Some(match instr.as_nibbles() {
+            (0x0, 0x0, 0xE, 0x0) => Opcode::ClearScreen,
+            (0x0, 0x0, 0xE, 0xE) => Opcode::Return,
+            (0x0, _, _, _) => Opcode::Sys(address),
+            (0x1, _, _, _) => Opcode::Jump(address),
+            (0x2, _, _, _) => Opcode::Call(address),
+            (0x3, x, _, _) => Opcode::SkipEqImmediate { x, value },
+            (0x4, x, _, _) => Opcode::SkipNeImmediate { x, value },
+            (0x5, x, y, 0x0) => Opcode::SkipEqRegister { x, y },
+            (0x6, x, _, _) => Opcode::SetImmediate { x, value },
+            (0x7, x, _, _) => Opcode::AddImmediate { x, value },
+            (0x8, x, y, 0x0) => Opcode::Copy { x, y },
+            (0x8, x, y, 0x1) => Opcode::Or { x, y },
+            (0x8, x, y, 0x2) => Opcode::And { x, y },
+            (0x8, x, y, 0x3) => Opcode::Xor { x, y },
+            (0x8, x, y, 0x4) => Opcode::Add { x, y },
+            (0x8, x, y, 0x5) => Opcode::Sub { x, y },
+            (0x8, x, y, 0x6) => Opcode::ShiftRight { x, y },
+            (0x8, x, y, 0x7) => Opcode::SubFrom { x, y },
+            (0x8, x, y, 0xE) => Opcode::ShiftLeft { x, y },
+            (0x9, x, y, 0x0) => Opcode::SkipNeRegister { x, y },
+            (0xA, _, _, _) => Opcode::SetIndex(address),
+            (0xB, x, _, _) => Opcode::JumpWithOffset { x, address },
+            (0xC, x, _, _) => Opcode::Random { x, mask: value },
+            (0xD, x, y, n) => Opcode::Display { x, y, n },
+            (0xE, x, 0x9, 0xE) => Opcode::SkipPressed { x },
+            (0xE, x, 0xA, 0x1) => Opcode::SkipNotPressed { x },
+            (0xF, n, 0x0, 0x1) => Opcode::SelectPlanes(n),
+            (0xF, x, 0x0, 0x7) => Opcode::ReadDelay { x },
+            (0xF, x, 0x0, 0xA) => Opcode::WaitKey { x },
+            (0xF, x, 0x1, 0x5) => Opcode::SetDelay { x },
+            (0xF, x, 0x1, 0x8) => Opcode::SetSound { x },
+            (0xF, x, 0x1, 0xE) => Opcode::AddIndex { x },
+            (0xF, x, 0x2, 0x9) => Opcode::FontCharacter { x },
+            (0xF, x, 0x3, 0x3) => Opcode::Bcd { x },
+            (0xF, x, 0x5, 0x5) => Opcode::Store { x },
+            (0xF, x, 0x6, 0x5) => Opcode::Load { x },
+            _ => return None,
+        })
+    }
+}
+
+impl fmt::Display for Opcode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Opcode::ClearScreen => write!(f, "CLS"),
+            Opcode::Return => write!(f, "RET"),
+            Opcode::Sys(address) => write!(f, "SYS {address:#05X}"),
+            Opcode::Jump(address) => write!(f, "JP {address:#05X}"),
+            Opcode::Call(address) => write!(f, "CALL {address:#05X}"),
+            Opcode::SkipEqImmediate { x, value } => write!(f, "SE V{x:X}, {value:#04X}"),
+            Opcode::SkipNeImmediate { x, value } => write!(f, "SNE V{x:X}, {value:#04X}"),
+            Opcode::SkipEqRegister { x, y } => write!(f, "SE V{x:X}, V{y:X}"),
+            Opcode::SetImmediate { x, value } => write!(f, "LD V{x:X}, {value:#04X}"),
+            Opcode::AddImmediate { x, value } => write!(f, "ADD V{x:X}, {value:#04X}"),
+            Opcode::Copy { x, y } => write!(f, "LD V{x:X}, V{y:X}"),
+            Opcode::Or { x, y } => write!(f, "OR V{x:X}, V{y:X}"),
+            Opcode::And { x, y } => write!(f, "AND V{x:X}, V{y:X}"),
+            Opcode::Xor { x, y } => write!(f, "XOR V{x:X}, V{y:X}"),
+            Opcode::Add { x, y } => write!(f, "ADD V{x:X}, V{y:X}"),
+            Opcode::Sub { x, y } => write!(f, "SUB V{x:X}, V{y:X}"),
+            Opcode::ShiftRight { x, y } => write!(f, "SHR V{x:X}, V{y:X}"),
+            Opcode::SubFrom { x, y } => write!(f, "SUBN V{x:X}, V{y:X}"),
+            Opcode::ShiftLeft { x, y } => write!(f, "SHL V{x:X}, V{y:X}"),
+            Opcode::SkipNeRegister { x, y } => write!(f, "SNE V{x:X}, V{y:X}"),
+            Opcode::SetIndex(address) => write!(f, "LD I, {address:#05X}"),
+            Opcode::JumpWithOffset { address, .. } => write!(f, "JP V0, {address:#05X}"),
+            Opcode::Random { x, mask } => write!(f, "RND V{x:X}, {mask:#04X}"),
+            Opcode::Display { x, y, n } => write!(f, "DRW V{x:X}, V{y:X}, {n}"),
+            Opcode::SkipPressed { x } => write!(f, "SKP V{x:X}"),
+            Opcode::SkipNotPressed { x } => write!(f, "SKNP V{x:X}"),
+            Opcode::SelectPlanes(n) => write!(f, "PLANE {n}"),
+            Opcode::ReadDelay { x } => write!(f, "LD V{x:X}, DT"),
+            Opcode::WaitKey { x } => write!(f, "LD V{x:X}, K"),
+            Opcode::SetDelay { x } => write!(f, "LD DT, V{x:X}"),
+            Opcode::SetSound { x } => write!(f, "LD ST, V{x:X}"),
+            Opcode::AddIndex { x } => write!(f, "ADD I, V{x:X}"),
+            Opcode::FontCharacter { x } => write!(f, "LD F, V{x:X}"),
+            Opcode::Bcd { x } => write!(f, "LD B, V{x:X}"),
+            Opcode::Store { x } => write!(f, "LD [I], V{x:X}"),
+            Opcode::Load { x } => write!(f, "LD V{x:X}, [I]"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode(b1: u8, b2: u8) -> Option<Opcode> {
+        Opcode::decode(&Instr::new(b1, b2))
+    }
+
+    #[test]
+    fn decodes_operands() {
+        assert_eq!(decode(0x00, 0xE0), Some(Opcode::ClearScreen));
+        assert_eq!(decode(0x00, 0xEE), Some(Opcode::Return));
+        assert_eq!(decode(0x1A, 0xBC), Some(Opcode::Jump(0xABC)));
+        assert_eq!(
+            decode(0x6A, 0x42),
+            Some(Opcode::SetImmediate {
+                x: 0xA,
+                value: 0x42
+            })
+        );
+        assert_eq!(decode(0x81, 0x2E), Some(Opcode::ShiftLeft { x: 1, y: 2 }));
+        assert_eq!(
+            decode(0xB3, 0x45),
+            Some(Opcode::JumpWithOffset {
+                x: 3,
+                address: 0x345
+            })
+        );
+        assert_eq!(
+            decode(0xD1, 0x25),
+            Some(Opcode::Display { x: 1, y: 2, n: 5 })
+        );
+        assert_eq!(decode(0xF2, 0x01), Some(Opcode::SelectPlanes(2)));
+        assert_eq!(decode(0xF7, 0x33), Some(Opcode::Bcd { x: 7 }));
+    }
+
+    #[test]
+    fn machine_code_is_not_unknown() {
+        assert_eq!(decode(0x01, 0x23), Some(Opcode::Sys(0x123)));
+        assert_eq!(decode(0x00, 0xE1), Some(Opcode::Sys(0x0E1)));
+    }
+
+    #[test]
+    fn unknown_opcodes_decode_to_none() {
+        for (b1, b2) in [
+            (0x51, 0x21),
+            (0x81, 0x28),
+            (0x91, 0x2F),
+            (0xE0, 0x00),
+            (0xF0, 0xFF),
+        ] {
+            assert_eq!(decode(b1, b2), None, "{b1:02X}{b2:02X}");
+        }
+    }
+
+    #[test]
+    fn jump_with_offset_names_the_register_it_adds() {
+        let op = decode(0xB3, 0x45).unwrap();
+        assert_eq!(op.to_string(), "JP V0, 0x345");
+        assert_eq!(op.mnemonic(&Config::default()), "JP V0, 0x345");
+        let quirky = Config {
+            jump_with_offset_register: true,
+            ..Config::default()
+        };
+        assert_eq!(op.mnemonic(&quirky), "JP V3, 0x345");
+        // nothing else depends on the quirk
+        let op = decode(0x13, 0x45).unwrap();
+        assert_eq!(op.mnemonic(&quirky), op.to_string());
+    }
+}
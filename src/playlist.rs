@@ -0,0 +1,189 @@
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::{Path, PathBuf},
+    str,
+    time::{Duration, Instant},
+};
+
+use serde::Deserialize;
+
+// the demo's name, which the window checks for to offer the roms in ROM_DIR
+pub const DEMO_NAME: &str = "demo, press enter to pick a rom";
+pub const ROM_DIR: &str = "programs";
+
+#[derive(Debug, Clone)]
+pub struct Rom {
+    pub name: String,
+    pub bytes: Vec<u8>,
+    // what the game uses each keypad key for, from its sidecar
+    pub controls: Vec<(u8, String)>,
+}
+
+// game.toml next to game.ch8
+#[derive(Debug, Deserialize)]
+struct Sidecar {
+    // hex key to what it does, `5 = "jump"`
+    #[serde(default)]
+    controls: HashMap<String, String>,
+}
+
+impl Rom {
+    pub fn load(path: &Path) -> io::Result<Self> {
+        Ok(Self {
+            name: path.file_name().map_or_else(
+                || path.display().to_string(),
+                |n| n.to_string_lossy().into(),
+            ),
+            bytes: fs::read(path)?,
+            controls: load_controls(&path.with_extension("toml"))?,
+        })
+    }
+
+    // "00E0 6001 A22A", the whitespace between bytes is optional
+    pub fn from_hex(text: &str) -> Option<Self> {
+        let digits: Vec<u8> = text.bytes().filter(|c| !c.is_ascii_whitespace()).collect();
+        if digits.is_empty() || !digits.len().is_multiple_of(2) {
+            return None;
+        }
+        let bytes = digits
+            .chunks(2)
+            .map(|pair| u8::from_str_radix(str::from_utf8(pair).ok()?, 16).ok())
+            .collect::<Option<_>>()?;
+        Some(Self {
+            name: "typed bytes".into(),
+            bytes,
+            controls: vec![],
+        })
+    }
+
+    // draws the font, then echoes each key pressed, to check the display and
+    // keypad work without needing a rom
+    pub fn demo() -> Self {
+        Self {
+            name: DEMO_NAME.into(),
+            bytes: DEMO.to_vec(),
+            controls: vec![],
+        }
+    }
+}
+
+// every .ch8 under `dir`, sorted, for the demo's rom picker. a missing or
+// unreadable directory just has nothing in it
+pub fn find_roms(dir: &Path) -> Vec<PathBuf> {
+    let mut roms = vec![];
+    let mut dirs = vec![dir.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        for path in entries.flatten().map(|entry| entry.path()) {
+            if path.is_dir() {
+                dirs.push(path);
+            } else if path.extension().is_some_and(|ext| ext == "ch8") {
+                roms.push(path);
+            }
+        }
+    }
+    roms.sort();
+    roms
+}
+
+// no sidecar is the same as one listing no controls
+fn load_controls(path: &Path) -> io::Result<Vec<(u8, String)>> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(e) => return Err(e),
+    };
+    let invalid = |e: String| io::Error::new(io::ErrorKind::InvalidData, e);
+    let sidecar: Sidecar = toml::from_str(&text).map_err(|e| invalid(e.to_string()))?;
+    let mut controls = sidecar
+        .controls
+        .into_iter()
+        .map(|(key, label)| match u8::from_str_radix(&key, 16) {
+            Ok(key) if key < 0x10 => Ok((key, label)),
+            _ => Err(invalid(format!("{key:?} isn't a keypad key"))),
+        })
+        .collect::<io::Result<Vec<_>>>()?;
+    controls.sort();
+    Ok(controls)
+}
+
+#[rustfmt::skip]
+const DEMO: [u8; 46] = [
+    0x00, 0xE0, // clear
+    0x60, 0x00, // V0 = glyph
+    0x61, 0x04, // V1 = x
+    0x62, 0x04, // V2 = y
+    0xF0, 0x29, // 0x208: I = glyph V0
+    0xD1, 0x25, // draw it
+    0x70, 0x01,
+    0x71, 0x07,
+    0x30, 0x08, // second row after 8 glyphs
+    0x12, 0x18,
+    0x61, 0x04,
+    0x62, 0x0C,
+    0x30, 0x10, // 0x218: until all 16 are drawn
+    0x12, 0x08,
+    0x63, 0x1D, // V3, V4 = where pressed keys go
+    0x64, 0x16,
+    0xF0, 0x0A, // V0 = key
+    0xF0, 0x29, // 0x222: draw it
+    0xD3, 0x45,
+    0xF1, 0x0A, // V1 = next key
+    0xD3, 0x45, // erase the last one
+    0x80, 0x10,
+    0x12, 0x22,
+];
+
+// cycles through a list of roms, giving each one `slot` of wall-clock time
+#[derive(Debug, Clone)]
+pub struct Playlist {
+    roms: Vec<Rom>,
+    current: usize,
+    slot: Duration,
+    started: Instant,
+}
+
+impl Playlist {
+    pub fn new(roms: Vec<Rom>, slot: Duration) -> Self {
+        assert!(!roms.is_empty(), "playlist needs at least one rom");
+        Self {
+            roms,
+            current: 0,
+            slot,
+            started: Instant::now(),
+        }
+    }
+
+    pub fn current(&self) -> &Rom {
+        &self.roms[self.current]
+    }
+
+    pub fn expired(&self) -> bool {
+        self.started.elapsed() >= self.slot
+    }
+
+    // moves `by` entries forward (or back if negative), wrapping at both ends
+    pub fn advance(&mut self, by: isize) -> &Rom {
+        let len = self.roms.len() as isize;
+        self.current = (self.current as isize + by).rem_euclid(len) as usize;
+        self.started = Instant::now();
+        self.current()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_the_bundled_roms() {
+        let roms = find_roms(Path::new(ROM_DIR));
+        assert!(roms.contains(&Path::new(ROM_DIR).join("tests/3-corax+.ch8")));
+        assert!(roms.iter().all(|rom| rom.extension().unwrap() == "ch8"));
+        assert!(roms.is_sorted());
+        assert_eq!(find_roms(Path::new("no such dir")), Vec::<PathBuf>::new());
+    }
+}
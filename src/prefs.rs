@@ -0,0 +1,73 @@
+use std::{fs, path::Path};
+
+use macroquad::color::Color;
+use serde::{Deserialize, Serialize};
+
+use crate::args::parse_color;
+
+// settings carried over from the last run, anything missing is left to the
+// command line or the defaults
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Prefs {
+    pub instrs_per_second: Option<f64>,
+    // "RRGGBB" for each pixel value, same as --palette
+    pub palette: Option<Vec<String>>,
+    // 0 to 1, same as --volume
+    pub volume: Option<f32>,
+}
+
+impl Prefs {
+    // a missing file is a first run, a broken one is warned about and ignored
+    pub fn load(path: &Path) -> Self {
+        let Ok(text) = fs::read_to_string(path) else {
+            return Self::default();
+        };
+        toml::from_str(&text).unwrap_or_else(|e| {
+            eprintln!("ignoring preferences in {}: {e}", path.display());
+            Self::default()
+        })
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let text = toml::to_string(self).map_err(|e| format!("could not save preferences: {e}"))?;
+        fs::write(path, text).map_err(|e| format!("could not write {}: {e}", path.display()))
+    }
+
+    pub fn palette(&self) -> Option<[Color; 4]> {
+        let colors = self.palette.as_ref()?;
+        let colors = colors
+            .iter()
+            .map(|c| parse_color(c))
+            .collect::<Option<Vec<_>>>()?;
+        colors.try_into().ok()
+    }
+
+    pub fn set_palette(&mut self, palette: &[Color; 4]) {
+        self.palette = Some(palette.iter().map(color_hex).collect());
+    }
+}
+
+fn color_hex(color: &Color) -> String {
+    let [r, g, b, _]: [u8; 4] = (*color).into();
+    format!("{r:02X}{g:02X}{b:02X}")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::*;
+
+    #[test]
+    fn volume_is_saved_and_restored() {
+        let path = env::temp_dir().join(format!("chip-8-prefs-{}.toml", std::process::id()));
+        let prefs = Prefs {
+            volume: Some(0.5),
+            ..Prefs::default()
+        };
+        prefs.save(&path).unwrap();
+        let loaded = Prefs::load(&path);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.volume, Some(0.5));
+    }
+}
@@ -1,4 +1,7 @@
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct Registers([u8; 16]);
 
 impl Registers {
@@ -6,15 +9,33 @@ impl Registers {
         Self([0; 16])
     }
 
+    pub fn randomize(&mut self, rng: &mut impl Rng) {
+        rng.fill(&mut self.0);
+    }
+
+    // register indices come from decoded nibbles, so anything past VF is a bug
+    // in the caller. catch it here rather than as an opaque slice panic.
+    fn index(x: u8) -> usize {
+        debug_assert!(x < 0x10, "register index {x:#X} is out of range");
+        x as usize
+    }
+
     pub fn get(&self, x: u8) -> u8 {
-        self.0[x as usize]
+        self.0[Self::index(x)]
     }
 
     pub fn get_mut(&mut self, x: u8) -> &mut u8 {
-        &mut self.0[x as usize]
+        &mut self.0[Self::index(x)]
     }
 
     pub fn set(&mut self, x: u8, v: u8) {
-        self.0[x as usize] = v;
+        self.0[Self::index(x)] = v;
+    }
+
+    // VF doubles as the carry/borrow/collision flag. Opcodes that write both
+    // VX and VF must call this after storing the result, so the flag wins
+    // when X is F.
+    pub fn set_vf(&mut self, v: u8) {
+        self.0[0xF] = v;
     }
 }
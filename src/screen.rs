@@ -1,20 +1,51 @@
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
-pub struct Screen(pub [u64; 32]);
+use serde::{Deserialize, Serialize};
+
+use crate::tern;
+
+// XO-CHIP has two bit planes, classic programs only ever touch the first
+pub const PLANES: usize = 2;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Screen(pub [[u64; 32]; PLANES]);
 
 impl Screen {
     pub fn new() -> Self {
-        Self([0; 32])
+        Self([[0; 32]; PLANES])
     }
 
     // returns the new state of the pixel
-    pub fn toggle(&mut self, x: u8, y: u8) -> bool {
+    pub fn toggle(&mut self, plane: usize, x: u8, y: u8) -> bool {
         let x = x as usize;
         let y = y as usize;
-        self.0[y] ^= 1 << x;
-        (self.0[y] & (1 << x)) != 0
+        self.0[plane][y] ^= 1 << x;
+        (self.0[plane][y] & (1 << x)) != 0
+    }
+
+    // bit n of the result is the pixel in plane n
+    pub fn pixel(&self, x: usize, y: usize) -> u8 {
+        (0..PLANES).fold(0, |v, plane| {
+            v | (((self.0[plane][y] >> x) & 1) as u8) << plane
+        })
+    }
+
+    // clears the planes selected by `planes`, bit n being plane n
+    pub fn clear(&mut self, planes: u8) {
+        for (plane, rows) in self.0.iter_mut().enumerate() {
+            if planes & (1 << plane) != 0 {
+                *rows = [0; 32];
+            }
+        }
     }
 
-    pub fn clear(&mut self) {
-        self.0 = [0; 32];
+    // one line per row, '#' for any lit pixel
+    pub fn to_ascii(&self) -> String {
+        let mut out = String::with_capacity(65 * 32);
+        for y in 0..32 {
+            for x in 0..64 {
+                out.push(tern!(self.pixel(x, y) != 0, '#', '.'));
+            }
+            out.push('\n');
+        }
+        out
     }
 }
@@ -0,0 +1,212 @@
+use std::{fs, path::Path, sync::atomic::Ordering};
+
+use rand_chacha::ChaCha12Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::{Chip8, Config, memory::Memory, registers::Registers, screen::Screen, tern};
+
+// everything a running rom can see, to put the machine back exactly as it was
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Snapshot {
+    pc: usize,
+    i: usize,
+    stack: Vec<usize>,
+    registers: Registers,
+    memory: Memory,
+    planes: u8,
+    screen: Screen,
+    delay_timer: u8,
+    sound_timer: u8,
+    rng: ChaCha12Rng,
+    // an FX0A that's part way through waiting
+    waiting_key: Option<u8>,
+    keys_at_last_wait: u16,
+    // quirks toggled since the snapshot go back with it
+    config: Config,
+}
+
+impl Snapshot {
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    // as toml, like the rest of what the emulator reads and writes
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let text = toml::to_string(self).map_err(|e| format!("could not save a snapshot: {e}"))?;
+        fs::write(path, text).map_err(|e| format!("could not write {}: {e}", path.display()))
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = fs::read_to_string(path)
+            .map_err(|e| format!("could not read {}: {e}", path.display()))?;
+        toml::from_str(&text).map_err(|e| format!("could not parse {}: {e}", path.display()))
+    }
+
+    // one "what: before -> after" line for everything that isn't the same in
+    // `other`, for tracking down where two runs went different ways
+    pub fn diff(&self, other: &Snapshot) -> Vec<String> {
+        let mut lines = Vec::new();
+        let mut changed = |what: &str, before: String, after: String| {
+            if before != after {
+                lines.push(format!("{what}: {before} -> {after}"));
+            }
+        };
+        changed(
+            "pc",
+            format!("{:#05X}", self.pc),
+            format!("{:#05X}", other.pc),
+        );
+        changed("I", format!("{:#05X}", self.i), format!("{:#05X}", other.i));
+        changed(
+            "stack",
+            format!("{:X?}", self.stack),
+            format!("{:X?}", other.stack),
+        );
+        for x in 0..0x10 {
+            let (before, after) = (self.registers.get(x), other.registers.get(x));
+            changed(
+                &format!("V{x:X}"),
+                format!("{before:#04X}"),
+                format!("{after:#04X}"),
+            );
+        }
+        changed(
+            "memory size",
+            self.memory.len().to_string(),
+            other.memory.len().to_string(),
+        );
+        for addr in 0..self.memory.len().min(other.memory.len()) {
+            let (before, after) = (self.memory.get(addr), other.memory.get(addr));
+            if before != after {
+                changed(
+                    &format!("[{addr:#05X}]"),
+                    format!("{before:#04X}"),
+                    format!("{after:#04X}"),
+                );
+            }
+        }
+        changed("planes", self.planes.to_string(), other.planes.to_string());
+        for (plane, (before, after)) in self.screen.0.iter().zip(&other.screen.0).enumerate() {
+            for (row, (before, after)) in before.iter().zip(after).enumerate() {
+                changed(
+                    &format!("plane {plane} row {row}"),
+                    row_ascii(*before),
+                    row_ascii(*after),
+                );
+            }
+        }
+        changed(
+            "delay timer",
+            self.delay_timer.to_string(),
+            other.delay_timer.to_string(),
+        );
+        changed(
+            "sound timer",
+            self.sound_timer.to_string(),
+            other.sound_timer.to_string(),
+        );
+        changed(
+            "waiting key",
+            format!("{:X?}", self.waiting_key),
+            format!("{:X?}", other.waiting_key),
+        );
+        changed(
+            "keys at last wait",
+            format!("{:016b}", self.keys_at_last_wait),
+            format!("{:016b}", other.keys_at_last_wait),
+        );
+        changed(
+            "config",
+            format!("{:?}", self.config),
+            format!("{:?}", other.config),
+        );
+        // nothing readable to show, but it decides what CXNN does next
+        if self.rng != other.rng {
+            lines.push("rng state differs".into());
+        }
+        lines
+    }
+}
+
+impl Chip8 {
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            pc: self.pc,
+            i: self.i,
+            stack: self.stack.clone(),
+            registers: self.registers.clone(),
+            memory: self.memory.clone(),
+            planes: self.planes,
+            screen: self.screen.lock().unwrap().clone(),
+            delay_timer: self.delay_timer.load(Ordering::Relaxed),
+            sound_timer: self.timers.lock().unwrap().sound_timer,
+            rng: self.rng.clone(),
+            waiting_key: self.waiting_key,
+            keys_at_last_wait: self.keys_at_last_wait,
+            config: self.config.clone(),
+        }
+    }
+
+    // only called from the compute thread, which takes the screen and timer
+    // locks one at a time like it does for any instruction
+    pub fn restore(&mut self, snapshot: Snapshot) {
+        self.pc = snapshot.pc;
+        self.i = snapshot.i;
+        self.stack = snapshot.stack;
+        self.registers = snapshot.registers;
+        self.memory = snapshot.memory;
+        self.planes = snapshot.planes;
+        self.rng = snapshot.rng;
+        self.waiting_key = snapshot.waiting_key;
+        self.keys_at_last_wait = snapshot.keys_at_last_wait;
+        self.shared.lock().unwrap().config = snapshot.config.clone();
+        self.config = snapshot.config;
+        *self.screen.lock().unwrap() = snapshot.screen;
+        self.delay_timer
+            .store(snapshot.delay_timer, Ordering::Relaxed);
+        // a fresh beep for whatever was left, not the one from back then
+        self.timers
+            .lock()
+            .unwrap()
+            .set_sound_timer(snapshot.sound_timer);
+    }
+}
+
+// a screen row the way Screen::to_ascii draws it, leftmost pixel first
+fn row_ascii(row: u64) -> String {
+    (0..64)
+        .map(|x| tern!(row >> x & 1 != 0, '#', '.'))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use crate::{builder::Chip8Builder, memory::MemorySize};
+
+    use super::*;
+
+    #[test]
+    fn round_trips_through_a_file() {
+        let config = Config {
+            memory_size: MemorySize::Extended,
+            rng_seed: Some(1),
+            ..Config::default()
+        };
+        let (mut chip8, _) = Chip8Builder::new().config(config).build().unwrap();
+        chip8.registers.set(3, 0x20);
+        chip8.memory.set(0xFFFF, 0xAB);
+        chip8.stack.push(0x222);
+        // the rightmost column is the top bit of a row, past i64::MAX
+        chip8.screen.lock().unwrap().toggle(1, 63, 31);
+        chip8.execute(0xC0, 0xFF);
+
+        let path = env::temp_dir().join(format!("chip-8-snapshot-{}.toml", std::process::id()));
+        let snapshot = chip8.snapshot();
+        snapshot.save(&path).unwrap();
+        let loaded = Snapshot::load(&path);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.unwrap(), snapshot);
+    }
+}
@@ -0,0 +1,35 @@
+use std::path::Path;
+
+// a png as sprite bytes, one byte a row when it's up to 8 pixels wide or two
+// when it's up to 16 like schip's big sprites. any height works, so a sheet of
+// sprites can be stacked in one image. pixels that are bright and not
+// transparent are lit
+pub fn from_png(path: &Path) -> Result<Vec<u8>, String> {
+    let image = image::open(path)
+        .map_err(|e| format!("could not read {}: {e}", path.display()))?
+        .to_luma_alpha8();
+    let (width, height) = image.dimensions();
+    let row_len = match width {
+        1..=8 => 1,
+        9..=16 => 2,
+        _ => {
+            return Err(format!(
+                "{} is {width} pixels wide, sprites are at most 16",
+                path.display()
+            ));
+        }
+    };
+
+    let mut bytes = vec![];
+    for y in 0..height {
+        let mut row = 0u16;
+        for x in 0..width {
+            let [luma, alpha] = image.get_pixel(x, y).0;
+            if luma >= 0x80 && alpha >= 0x80 {
+                row |= 0x8000 >> x;
+            }
+        }
+        bytes.extend(&row.to_be_bytes()[..row_len]);
+    }
+    Ok(bytes)
+}
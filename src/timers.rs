@@ -0,0 +1,86 @@
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicU8, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+// a beep as requested by FX18, independent of how often the timer is polled
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Beep {
+    pub start: Instant,
+    pub duration: Duration,
+}
+
+impl Beep {
+    pub fn remaining(&self) -> Duration {
+        (self.start + self.duration).saturating_duration_since(Instant::now())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Timers {
+    // shared outside the lock so games polling FX07 in a tight loop don't
+    // fight the timer thread for it
+    delay_timer: Arc<AtomicU8>,
+    pub sound_timer: u8,
+    // how often the sound timer counts down, the delay timer is always 60Hz
+    pub sound_hz: f64,
+    beep: Option<Beep>,
+}
+
+impl Timers {
+    pub fn new() -> Self {
+        Self {
+            delay_timer: Arc::new(AtomicU8::new(0)),
+            sound_timer: 0,
+            sound_hz: 60.0,
+            beep: None,
+        }
+    }
+
+    // zeroes both timers but keeps the sound timer's rate, and the delay
+    // timer's handle still pointing at the same value
+    pub fn reset(&mut self) {
+        self.delay_timer.store(0, Ordering::Relaxed);
+        self.sound_timer = 0;
+        self.beep = None;
+    }
+
+    // the delay timer, readable and writable without locking the rest
+    pub fn delay_timer(&self) -> Arc<AtomicU8> {
+        Arc::clone(&self.delay_timer)
+    }
+
+    pub fn tick_delay(&mut self) {
+        // only fails when already at zero, which is fine to leave
+        let _ = self
+            .delay_timer
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |t| t.checked_sub(1));
+    }
+
+    pub fn tick_sound(&mut self) {
+        self.sound_timer = self.sound_timer.saturating_sub(1);
+    }
+
+    pub fn set_sound_timer(&mut self, v: u8) {
+        self.sound_timer = v;
+        self.beep = (v != 0).then(|| Beep {
+            start: Instant::now(),
+            duration: Duration::from_secs_f64(v as f64 / self.sound_hz),
+        });
+    }
+
+    // the last beep, so short ones (1-2 ticks) can still be heard in full even
+    // if the timer has already run out by the time someone looks
+    pub fn beep(&self) -> Option<Beep> {
+        self.beep
+    }
+
+    // whether the tone should be on. that's for as long as the sound timer is
+    // non-zero, however slowly it's ticking, with the beep as a minimum
+    pub fn sounding(&self) -> bool {
+        self.sound_timer != 0 || self.beep.is_some_and(|beep| !beep.remaining().is_zero())
+    }
+}
@@ -0,0 +1,159 @@
+use std::{
+    fmt::Write,
+    time::{Duration, Instant},
+};
+
+// how long each execute_instr call took, kept as a histogram so a long run
+// doesn't grow without bound
+#[derive(Debug, Clone)]
+pub struct InstrTimes {
+    count: u64,
+    total: Duration,
+    min: Duration,
+    max: Duration,
+    // bucket n counts calls that took under 2^n nanoseconds
+    buckets: [u64; 64],
+}
+
+impl InstrTimes {
+    pub fn new() -> Self {
+        Self {
+            count: 0,
+            total: Duration::ZERO,
+            min: Duration::MAX,
+            max: Duration::ZERO,
+            buckets: [0; 64],
+        }
+    }
+
+    pub fn record(&mut self, time: Duration) {
+        self.count += 1;
+        self.total += time;
+        self.min = self.min.min(time);
+        self.max = self.max.max(time);
+        let nanos = time.as_nanos().min(u64::MAX as u128) as u64;
+        let bucket = (u64::BITS - nanos.leading_zeros()).min(63);
+        self.buckets[bucket as usize] += 1;
+    }
+
+    // upper bound of the bucket the `p`th fraction of calls falls in, so it's
+    // within a factor of two of the real value
+    fn percentile(&self, p: f64) -> Duration {
+        let target = (self.count as f64 * p).ceil() as u64;
+        let mut seen = 0;
+        for (bucket, count) in self.buckets.iter().enumerate() {
+            seen += count;
+            if seen >= target {
+                return Duration::from_nanos(1 << bucket).min(self.max);
+            }
+        }
+        self.max
+    }
+
+    pub fn summary(&self) -> String {
+        let mut out = String::new();
+        if self.count == 0 {
+            writeln!(out, "no instructions timed").unwrap();
+            return out;
+        }
+        let avg = Duration::from_secs_f64(self.total.as_secs_f64() / self.count as f64);
+        writeln!(out, "instruction times over {} calls:", self.count).unwrap();
+        writeln!(out, "    min  {:?}", self.min).unwrap();
+        writeln!(out, "    avg  {avg:?}").unwrap();
+        writeln!(out, "    p99 <{:?}", self.percentile(0.99)).unwrap();
+        writeln!(out, "    max  {:?}", self.max).unwrap();
+        out
+    }
+}
+
+// when the next instruction is due, so the compute thread can sleep until
+// then. it's handed the time rather than reading the clock itself
+#[derive(Debug, Clone)]
+pub struct Pacer {
+    next_time: Instant,
+    // instructions run so far in this frame's burst, and when it started
+    burst: u32,
+    burst_start: Instant,
+}
+
+impl Pacer {
+    pub fn new(now: Instant) -> Self {
+        Self {
+            next_time: now,
+            burst: 0,
+            burst_start: now,
+        }
+    }
+
+    // after a pause, so the time spent paused isn't made up for afterwards
+    pub fn restart(&mut self, now: Instant) {
+        self.next_time = now;
+    }
+
+    // called as each instruction finishes. returns when the next one is due
+    // and, at the end of an instrs_per_frame burst, how long the burst took
+    pub fn after_instr(
+        &mut self,
+        now: Instant,
+        instrs_per_second: f64,
+        instrs_per_frame: Option<u32>,
+        time_scale: f64,
+        fast_forward: bool,
+    ) -> (Instant, Option<Duration>) {
+        // keeps up with the clock too, or letting go of fast forward would
+        // run flat out until the schedule caught up with it
+        if fast_forward {
+            self.restart(now);
+            return (now, None);
+        }
+        let Some(ipf) = instrs_per_frame else {
+            self.next_time += Duration::from_secs_f64(1.0 / instrs_per_second / time_scale);
+            return (self.next_time, None);
+        };
+        self.burst += 1;
+        if self.burst < ipf {
+            return (now, None);
+        }
+        self.burst = 0;
+        let burst_time = now - self.burst_start;
+        self.next_time += Duration::from_secs_f64(1.0 / 60.0 / time_scale);
+        self.burst_start = self.next_time.max(now);
+        (self.next_time, Some(burst_time))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // instructions run in one simulated second, each taking 20us and
+    // then sleeping for as long as the pacer says
+    fn instrs_in_a_second(instrs_per_frame: Option<u32>, fast_forward: bool) -> u32 {
+        let start = Instant::now();
+        let mut pacer = Pacer::new(start);
+        let mut now = start;
+        let mut instrs = 0;
+        while now < start + Duration::from_secs(1) {
+            now += Duration::from_micros(20);
+            instrs += 1;
+            let (next, _) = pacer.after_instr(now, 700.0, instrs_per_frame, 1.0, fast_forward);
+            now = now.max(next);
+        }
+        instrs
+    }
+
+    #[test]
+    fn paces_instrs_per_second() {
+        assert!((693..=707).contains(&instrs_in_a_second(None, false)));
+    }
+
+    #[test]
+    fn paces_instrs_per_frame() {
+        assert!((594..=606).contains(&instrs_in_a_second(Some(10), false)));
+    }
+
+    #[test]
+    fn fast_forward_runs_more() {
+        assert!(instrs_in_a_second(None, true) > instrs_in_a_second(None, false));
+    }
+}
@@ -1,89 +1,875 @@
 use std::{
+    collections::{HashSet, VecDeque},
+    path::{Path, PathBuf},
     sync::{Arc, Mutex},
-    time::Instant,
+    time::{Duration, Instant},
 };
 
 use macroquad::prelude::*;
 
-use crate::{Shared, keys::Keys, screen::Screen};
+use crate::{
+    Edit, Quirk, RunUntil, Shared,
+    args::{parse_number, parse_poke, parse_register_assignment},
+    audio::{Beeper, VOLUME_STEP},
+    builder::Handles,
+    console::Console,
+    hotkeys::Hotkeys,
+    instr::Instr,
+    keys::Keys,
+    opcode::Opcode,
+    playlist::{self, DEMO_NAME, ROM_DIR, Rom},
+    prefs::Prefs,
+    screen::Screen,
+    tern,
+    timers::Timers,
+};
 
 const CONFIG_PANEL_RATIO: f32 = 0.4;
 
+// qwerty, for when --keymap isn't given
+pub const KEY_MAP: [KeyCode; 0x10] = [
+    KeyCode::X,    // 0
+    KeyCode::Key1, // 1
+    KeyCode::Key2, // 2
+    KeyCode::Key3, // 3
+    KeyCode::Q,    // 4
+    KeyCode::W,    // 5
+    KeyCode::E,    // 6
+    KeyCode::A,    // 7
+    KeyCode::S,    // 8
+    KeyCode::D,    // 9
+    KeyCode::Z,    // A
+    KeyCode::C,    // B
+    KeyCode::Key4, // C
+    KeyCode::R,    // D
+    KeyCode::F,    // E
+    KeyCode::V,    // F
+];
+
+// for debugging key opcodes, each key is the chip-8 key it's labeled with
+const HEX_KEY_MAP: [KeyCode; 0x10] = [
+    KeyCode::Key0,
+    KeyCode::Key1,
+    KeyCode::Key2,
+    KeyCode::Key3,
+    KeyCode::Key4,
+    KeyCode::Key5,
+    KeyCode::Key6,
+    KeyCode::Key7,
+    KeyCode::Key8,
+    KeyCode::Key9,
+    KeyCode::A,
+    KeyCode::B,
+    KeyCode::C,
+    KeyCode::D,
+    KeyCode::E,
+    KeyCode::F,
+];
+
+// how long a pixel takes to fade out after being cleared, with ghosting on
+const GHOST_FADE_SECS: f64 = 0.1;
+
+// palettes the theme key cycles through, the first is the default
+pub const THEMES: [(&str, [Color; 4]); 3] = [
+    (
+        "white",
+        [
+            BLACK,
+            WHITE,
+            Color::from_hex(0xAAAAAA),
+            Color::from_hex(0x555555),
+        ],
+    ),
+    (
+        "green",
+        [
+            Color::from_hex(0x0A1A0A),
+            Color::from_hex(0x33FF33),
+            Color::from_hex(0x1A8C1A),
+            Color::from_hex(0x99FF99),
+        ],
+    ),
+    (
+        "amber",
+        [
+            Color::from_hex(0x1A0F00),
+            Color::from_hex(0xFFB000),
+            Color::from_hex(0x996A00),
+            Color::from_hex(0xFFD27F),
+        ],
+    ),
+];
+
+#[derive(Debug, Clone)]
+pub struct RenderConfig {
+    // fade cleared pixels out over a few frames instead of blanking them, to
+    // hide the flicker from sprites being erased and redrawn
+    pub ghosting: bool,
+    // indexed by pixel value: background, plane 1, plane 2, both planes
+    pub palette: [Color; 4],
+    // everything around the playfield, the side panel included
+    pub border: Color,
+    // draw pixels as round dots like a crt's phosphor instead of squares
+    pub crt_dots: bool,
+}
+
+impl RenderConfig {
+    // None for a palette that isn't one of the themes
+    fn theme(&self) -> Option<usize> {
+        THEMES
+            .iter()
+            .position(|(_, palette)| *palette == self.palette)
+    }
+
+    // a custom palette goes to the first theme
+    fn next_theme(&mut self) {
+        let next = self.theme().map_or(0, |n| (n + 1) % THEMES.len());
+        self.palette = THEMES[next].1;
+    }
+}
+
+impl Default for RenderConfig {
+    fn default() -> Self {
+        Self {
+            ghosting: false,
+            palette: THEMES[0].1,
+            border: BLACK,
+            crt_dots: false,
+        }
+    }
+}
+
+// slowest slow motion goes, 1/64 speed
+const MIN_TIME_SCALE: f64 = 1.0 / 64.0;
+
+// range the console's speed command clamps to
+const MIN_SPEED: f64 = 1.0;
+const MAX_SPEED: f64 = 1_000_000.0;
+
+// frames of stack depth the hud graphs
+const STACK_HISTORY_LEN: usize = 180;
+
+// how long the compute thread can go without running anything, while not
+// paused, before the panel says it's stalled
+const STALL_TIMEOUT: Duration = Duration::from_secs(2);
+
+// watches the instruction odometer to tell a hung compute thread apart from
+// a program that's just idle
+struct Watchdog {
+    last_count: u64,
+    last_change: Instant,
+}
+
+impl Watchdog {
+    fn new() -> Self {
+        Self {
+            last_count: 0,
+            last_change: Instant::now(),
+        }
+    }
+
+    fn stalled(&mut self, options: &Shared) -> bool {
+        if options.paused || options.instrs_executed != self.last_count {
+            self.last_count = options.instrs_executed;
+            self.last_change = Instant::now();
+        }
+        self.last_change.elapsed() > STALL_TIMEOUT
+    }
+}
+
 pub async fn window_main(
-    screen: Arc<Mutex<Screen>>,
-    options: Arc<Mutex<Shared>>,
-    keys: Arc<Mutex<Keys>>,
+    handles: Handles,
+    hotkeys: Hotkeys,
+    key_map: [KeyCode; 0x10],
+    mut render: RenderConfig,
+    prefs: Option<PathBuf>,
+    mut beeper: Option<Beeper>,
 ) {
+    let Handles {
+        screen,
+        timers,
+        shared: options,
+        keys,
+    } = handles;
+    let mut show_hud = false;
+    let mut watchdog = Watchdog::new();
+    let mut hex_input = false;
+    let mut show_coverage = false;
+    let mut stack_history = VecDeque::with_capacity(STACK_HISTORY_LEN);
+    let mut console = Console::default();
+    // when each pixel was last seen lit and with what value, for ghosting
+    let mut last_lit = [[(f64::NEG_INFINITY, 0); 64]; 32];
+    prevent_quit();
     loop {
-        handle_user_input(options.clone(), keys.clone());
-        clear_background(BLACK);
+        if is_quit_requested() {
+            let options = options.lock().unwrap();
+            if options.skipped_instrs != 0 {
+                println!(
+                    "skipped {} illegal or machine code instructions",
+                    options.skipped_instrs
+                );
+            }
+            if let Some(times) = &options.instr_times {
+                print!("{}", times.summary());
+            }
+            if let Some(path) = &prefs {
+                // starting from what's there keeps the volume from a run
+                // with sound when this one was muted
+                let mut saved = Prefs::load(path);
+                saved.instrs_per_second = Some(options.instrs_per_second);
+                saved.set_palette(&render.palette);
+                if let Some(beeper) = &beeper {
+                    saved.volume = Some(beeper.volume());
+                }
+                if let Err(e) = saved.save(path) {
+                    eprintln!("{e}");
+                }
+            }
+            return;
+        }
+
+        if console.open {
+            keys.lock().unwrap().set(0);
+            if let Some(line) = console.update() {
+                console.report(run_command(&line, &options));
+            }
+        } else {
+            let key_map = tern!(hex_input, &HEX_KEY_MAP, &key_map);
+            handle_user_input(options.clone(), keys.clone(), &hotkeys, key_map);
+            if is_key_pressed(hotkeys.hex_input) {
+                hex_input = !hex_input;
+            }
+            if is_key_pressed(hotkeys.copy_screen) {
+                copy_screen(&screen.lock().unwrap());
+            }
+            if is_key_pressed(hotkeys.coverage) {
+                show_coverage = !show_coverage;
+            }
+            if is_key_pressed(hotkeys.hud) {
+                show_hud = !show_hud;
+            }
+            if is_key_pressed(hotkeys.ghosting) {
+                render.ghosting = !render.ghosting;
+            }
+            if is_key_pressed(hotkeys.theme) {
+                render.next_theme();
+            }
+            if is_key_pressed(hotkeys.console) {
+                // leaving the demo goes to whatever roms are around to pick
+                if options.lock().unwrap().rom_name == DEMO_NAME {
+                    let roms = playlist::find_roms(Path::new(ROM_DIR));
+                    console.pick_from(roms.iter().map(|r| r.display().to_string()).collect());
+                } else {
+                    console.toggle();
+                }
+            }
+            if let Some(beeper) = &mut beeper {
+                let step = match (
+                    is_key_pressed(hotkeys.volume_up),
+                    is_key_pressed(hotkeys.volume_down),
+                ) {
+                    (true, false) => VOLUME_STEP,
+                    (false, true) => -VOLUME_STEP,
+                    _ => 0.0,
+                };
+                if step != 0.0 {
+                    beeper.set_volume(beeper.volume() + step);
+                    println!("volume: {:.0}%", beeper.volume() * 100.0);
+                }
+            }
+        }
+        clear_background(render.border);
 
-        let dx = screen_width() * (1.0 - CONFIG_PANEL_RATIO) / 64.0;
-        let dy = screen_height() / 32.0;
+        let display = display_rect();
+        draw_rectangle(
+            display.x,
+            display.y,
+            display.w,
+            display.h,
+            render.palette[0],
+        );
 
-        draw_screen(screen.clone(), dx, dy);
+        draw_screen(screen.clone(), display, &render, &mut last_lit);
 
-        draw_panel(options.clone());
+        let stalled = {
+            let options = options.lock().unwrap();
+            if stack_history.len() == STACK_HISTORY_LEN {
+                stack_history.pop_front();
+            }
+            stack_history.push_back(options.stack_depth);
+            watchdog.stalled(&options)
+        };
+        let key_map = tern!(hex_input, &HEX_KEY_MAP, &key_map);
+        draw_panel(options.clone(), timers.clone(), stalled, key_map, &render);
+        if show_hud {
+            draw_hud(options.clone(), &stack_history);
+        }
+        if show_coverage {
+            draw_coverage(options.lock().unwrap().opcodes_seen, display.right());
+        }
+        if hex_input {
+            draw_keys_down(&keys.lock().unwrap(), display.bottom());
+        }
+        console.draw(screen_width() * (1.0 - CONFIG_PANEL_RATIO));
+
+        if let Some(beeper) = &mut beeper {
+            beeper.set(timers.lock().unwrap().sounding());
+        }
 
         next_frame().await;
+        options.lock().unwrap().frame_count += 1;
     }
 }
 
-fn draw_screen(screen: Arc<Mutex<Screen>>, dx: f32, dy: f32) {
-    for (y, row) in screen.lock().unwrap().0.into_iter().enumerate() {
-        for i in 0..64 {
-            if row & (1 << i) != 0 {
-                draw_rectangle(i as f32 * dx, y as f32 * dy, dx, dy, WHITE);
+// the biggest 2:1 area that fits left of the panel, centered so the rest is
+// border
+fn display_rect() -> Rect {
+    let (width, height) = (screen_width() * (1.0 - CONFIG_PANEL_RATIO), screen_height());
+    // never below a screen pixel each, so a tiny window still draws something
+    let scale = (width / 64.0).min(height / 32.0).max(1.0);
+    let (w, h) = (scale * 64.0, scale * 32.0);
+    Rect::new(
+        ((width - w) / 2.0).max(0.0),
+        ((height - h) / 2.0).max(0.0),
+        w,
+        h,
+    )
+}
+
+fn draw_screen(
+    screen: Arc<Mutex<Screen>>,
+    display: Rect,
+    render: &RenderConfig,
+    last_lit: &mut [[(f64, u8); 64]; 32],
+) {
+    let size = display.w / 64.0;
+    let now = get_time();
+    let screen = screen.lock().unwrap();
+    for (y, row) in last_lit.iter_mut().enumerate() {
+        for (x, (lit_at, lit_value)) in row.iter_mut().enumerate() {
+            let value = screen.pixel(x, y);
+            let color = if value != 0 {
+                *lit_at = now;
+                *lit_value = value;
+                render.palette[value as usize]
+            } else if render.ghosting && now - *lit_at < GHOST_FADE_SECS {
+                let fade = 1.0 - (now - *lit_at) / GHOST_FADE_SECS;
+                let mut color = render.palette[*lit_value as usize];
+                color.a *= fade as f32;
+                color
+            } else {
+                continue;
+            };
+            let (px, py) = (display.x + x as f32 * size, display.y + y as f32 * size);
+            if render.crt_dots {
+                let radius = size / 2.0;
+                draw_circle(px + radius, py + radius, radius, color);
+            } else {
+                draw_rectangle(px, py, size, size, color);
             }
         }
     }
 }
 
-fn draw_panel(options: Arc<Mutex<Shared>>) {
+// pc and the instruction about to run in the corner of the display, with the
+// stack depth over the last few seconds under it
+fn draw_hud(options: Arc<Mutex<Shared>>, stack_history: &VecDeque<usize>) {
+    let (pc, i, [b1, b2], depth, max_depth) = {
+        let options = options.lock().unwrap();
+        (
+            options.pc,
+            options.i,
+            options.next_instr,
+            options.stack_depth,
+            options.max_stack_depth,
+        )
+    };
+    let text = format!("{pc:#05X}  {}", Instr::new(b1, b2).disassemble());
+    draw_rectangle(0.0, 0.0, 260.0, 92.0, Color::new(0.0, 0.0, 0.0, 0.6));
+    draw_text(&text, 5.0, 18.0, 20.0, YELLOW);
+    let stack_text = format!("I={i:#05X}  stack: {depth} (deepest {max_depth})");
+    draw_text(&stack_text, 5.0, 40.0, 20.0, YELLOW);
+
+    let bar_width = 210.0 / STACK_HISTORY_LEN as f32;
+    let scale = 40.0 / max_depth.max(1) as f32;
+    for (i, depth) in stack_history.iter().enumerate() {
+        let height = *depth as f32 * scale;
+        draw_rectangle(
+            5.0 + i as f32 * bar_width,
+            87.0 - height,
+            bar_width,
+            height,
+            YELLOW,
+        );
+    }
+}
+
+// a cell per opcode along the top right of the display, lit once it's run
+fn draw_coverage(seen: u64, right: f32) {
+    const COLUMNS: usize = 6;
+    const CELL: (f32, f32) = (46.0, 20.0);
+    let left = right - CELL.0 * COLUMNS as f32;
+    let rows = Opcode::PATTERNS.len().div_ceil(COLUMNS);
+    draw_rectangle(
+        left,
+        0.0,
+        CELL.0 * COLUMNS as f32,
+        CELL.1 * rows as f32,
+        Color::new(0.0, 0.0, 0.0, 0.6),
+    );
+    for (n, pattern) in Opcode::PATTERNS.iter().enumerate() {
+        let x = left + (n % COLUMNS) as f32 * CELL.0;
+        let y = (n / COLUMNS) as f32 * CELL.1;
+        let color = tern!(seen & (1 << n) != 0, GREEN, DARKGRAY);
+        draw_text(pattern, x + 3.0, y + 15.0, 18.0, color);
+    }
+}
+
+// which chip-8 keys are held, along the bottom of the display
+fn draw_keys_down(keys: &Keys, bottom: f32) {
+    let down = keys.as_u16();
+    let names = (0..0x10)
+        .filter(|key| down & (1 << key) != 0)
+        .map(|key| format!("{key:X}"))
+        .collect::<Vec<_>>();
+    let text = format!("hex keys: {}", names.join(" "));
+    draw_rectangle(
+        0.0,
+        bottom - 26.0,
+        220.0,
+        26.0,
+        Color::new(0.0, 0.0, 0.0, 0.6),
+    );
+    draw_text(&text, 5.0, bottom - 8.0, 20.0, YELLOW);
+}
+
+fn draw_panel(
+    options: Arc<Mutex<Shared>>,
+    timers: Arc<Mutex<Timers>>,
+    stalled: bool,
+    key_map: &[KeyCode; 0x10],
+    render: &RenderConfig,
+) {
     let start_x = screen_width() * (1.0 - CONFIG_PANEL_RATIO);
     let mut y = 30.0;
+    let rom_text = format!("rom: {}", options.lock().unwrap().rom_name);
+    let size = draw_text(&rom_text, start_x, y, 20.0, WHITE);
+    y += size.height + 10.0;
+    // the keyboard key each control is on right now
+    for (key, label) in &options.lock().unwrap().controls {
+        let control_text = format!("{:?} ({key:X}): {label}", key_map[*key as usize]);
+        let size = draw_text(&control_text, start_x, y, 20.0, YELLOW);
+        y += size.height + 10.0;
+    }
+    // the two presets from --accuracy and --smooth, or neither of their
+    // settings on
+    let display_wait = options.lock().unwrap().config.display_wait;
+    let mode = match (display_wait, render.ghosting) {
+        (true, false) => "accuracy",
+        (false, true) => "smooth",
+        (false, false) => "default",
+        (true, true) => "custom",
+    };
+    let mode_text = format!("display: {mode}");
+    let size = draw_text(&mode_text, start_x, y, 20.0, WHITE);
+    y += size.height + 10.0;
+    let theme = render.theme().map_or("custom", |n| THEMES[n].0);
+    let size = draw_text(&format!("theme: {theme}"), start_x, y, 20.0, WHITE);
+    y += size.height + 10.0;
     let fpx_text = format!("FPS: {:.2}", 1.0 / get_frame_time());
     let size = draw_text(&fpx_text, start_x, y, 20.0, WHITE);
     y += size.height + 10.0;
     let instrs_per_second;
+    let instrs_per_frame;
     let instr_count;
     let count_start;
+    let instrs_executed;
+    let skipped_instrs;
+    let frame_count;
+    let started;
+    let paused;
+    let position;
+    let timers_paused;
+    let time_scale;
+    let fast_booting;
+    let burst_time;
+    let mut config;
     {
         let options = options.lock().unwrap();
         instrs_per_second = options.instrs_per_second;
+        instrs_per_frame = options.instrs_per_frame;
         instr_count = options.instr_count;
         count_start = options.count_start;
+        instrs_executed = options.instrs_executed;
+        skipped_instrs = options.skipped_instrs;
+        frame_count = options.frame_count;
+        started = options.started;
+        paused = options.paused;
+        position = (options.pc, options.i, options.next_instr);
+        timers_paused = options.timers_paused;
+        time_scale = options.time_scale;
+        fast_booting = options.fast_booting;
+        burst_time = options.burst_time;
+        config = options.config.clone();
     }
-    let speed_target_text = format!("speed target: {} / sec", instrs_per_second.round());
+    let frames_text = format!("frames: {frame_count}");
+    let size = draw_text(&frames_text, start_x, y, 20.0, WHITE);
+    y += size.height + 10.0;
+
+    let uptime_text = format!("uptime: {:.1}s", started.elapsed().as_secs_f64());
+    let size = draw_text(&uptime_text, start_x, y, 20.0, WHITE);
+    y += size.height + 10.0;
+
+    let speed_target_text = match instrs_per_frame {
+        Some(ipf) => format!("speed target: {ipf} / frame ({instrs_per_second} / sec)"),
+        None => format!("speed target: {} / sec", instrs_per_second.round()),
+    };
     let size = draw_text(&speed_target_text, start_x, y, 20.0, WHITE);
     y += size.height + 10.0;
 
+    // how much of each frame the burst uses, the rest is headroom for more
+    if instrs_per_frame.is_some() {
+        let budget = 1000.0 / 60.0 / time_scale;
+        let burst_text = format!(
+            "batch: {:.1}ms / {budget:.1}ms",
+            burst_time.as_secs_f64() * 1000.0
+        );
+        let size = draw_text(&burst_text, start_x, y, 20.0, WHITE);
+        y += size.height + 10.0;
+    }
+
+    if time_scale < 1.0 {
+        let slow_text = format!("slow motion: 1/{}", (1.0 / time_scale).round());
+        let size = draw_text(&slow_text, start_x, y, 20.0, WHITE);
+        y += size.height + 10.0;
+    }
+
+    if fast_booting {
+        let size = draw_text(
+            "fast boot: hurrying a splash screen",
+            start_x,
+            y,
+            20.0,
+            WHITE,
+        );
+        y += size.height + 10.0;
+    }
+
     let instr_speed = instr_count as f64 / (Instant::now() - count_start).as_secs_f64();
 
     let instr_speed_text = format!("actual speed: {} / sec", instr_speed.round());
 
-    draw_text(&instr_speed_text, start_x, y, 20.0, WHITE);
+    let size = draw_text(&instr_speed_text, start_x, y, 20.0, WHITE);
+    y += size.height + 10.0;
+
+    let executed_text = format!("executed: {instrs_executed}");
+    let size = draw_text(&executed_text, start_x, y, 20.0, WHITE);
+    y += size.height + 10.0;
+
+    if skipped_instrs != 0 {
+        let skipped_text = format!("skipped: {skipped_instrs}");
+        let size = draw_text(&skipped_text, start_x, y, 20.0, WHITE);
+        y += size.height + 10.0;
+    }
+
+    let size = draw_text("quirks:", start_x, y, 20.0, WHITE);
+    y += size.height + 10.0;
+    for quirk in Quirk::ALL {
+        let on = *quirk.flag(&mut config);
+        let quirk_text = format!("  [{}] {}", tern!(on, "x", " "), quirk.name());
+        let size = draw_text(&quirk_text, start_x, y, 20.0, tern!(on, GREEN, GRAY));
+        y += size.height + 10.0;
+    }
+
+    if let Some(beep) = timers.lock().unwrap().beep()
+        && !beep.remaining().is_zero()
+    {
+        let beep_text = format!("sound: {}ms", beep.remaining().as_millis());
+        let size = draw_text(&beep_text, start_x, y, 20.0, WHITE);
+        y += size.height + 10.0;
+    }
+    if paused {
+        let size = draw_text("paused", start_x, y, 20.0, WHITE);
+        y += size.height + 10.0;
+        // where the next step goes from
+        let (pc, i, [b1, b2]) = position;
+        let next_text = format!("{pc:#05X}: {}", Instr::new(b1, b2).disassemble());
+        let size = draw_text(&next_text, start_x, y, 20.0, WHITE);
+        y += size.height + 10.0;
+        let i_text = format!("I: {i:#05X}");
+        let size = draw_text(&i_text, start_x, y, 20.0, WHITE);
+        y += size.height + 10.0;
+    }
+    if timers_paused {
+        let size = draw_text("timers paused", start_x, y, 20.0, WHITE);
+        y += size.height + 10.0;
+    }
+    if stalled {
+        draw_text("compute stalled", start_x, y, 20.0, RED);
+    }
 }
 
-fn handle_user_input(options: Arc<Mutex<Shared>>, keys: Arc<Mutex<Keys>>) {
+fn handle_user_input(
+    options: Arc<Mutex<Shared>>,
+    keys: Arc<Mutex<Keys>>,
+    hotkeys: &Hotkeys,
+    key_map: &[KeyCode; 0x10],
+) {
     let pressed = get_keys_pressed();
 
-    keys.lock().unwrap().set(get_keys_down());
+    *keys.lock().unwrap() = Keys::from_u16(keypad_mask(key_map, &get_keys_down()));
 
     let mut options = options.lock().unwrap();
-    if pressed.contains(&KeyCode::Tab) {
+    if pressed.contains(&hotkeys.fast_forward) {
         options.fast_forward = !options.fast_forward;
     }
+    if pressed.contains(&hotkeys.pause) {
+        options.paused = !options.paused;
+        options.run_until = None;
+    }
+    // the timer thread holds the timers still while paused, so stepping
+    // doesn't see them run down in the meantime
+    if options.paused && pressed.contains(&hotkeys.step) {
+        options.run_until = Some(RunUntil::Step);
+    }
+    if options.paused && pressed.contains(&hotkeys.step_to_draw) {
+        options.run_until = Some(RunUntil::Draw);
+    }
+    if options.paused && pressed.contains(&hotkeys.step_to_key_read) {
+        options.run_until = Some(RunUntil::KeyRead);
+    }
+    let quirk_keys = [
+        hotkeys.quirk_bitshift_copies_y,
+        hotkeys.quirk_shift_vf_from_vx,
+        hotkeys.quirk_jump_with_offset_register,
+        hotkeys.quirk_update_i_after_store_or_load,
+    ];
+    for (key, quirk) in quirk_keys.iter().zip(Quirk::ALL) {
+        if pressed.contains(key) {
+            options.edits.push(Edit::ToggleQuirk(quirk));
+        }
+    }
+    if pressed.contains(&hotkeys.save_snapshot) {
+        options.edits.push(Edit::SaveSnapshot);
+    }
+    if pressed.contains(&hotkeys.load_snapshot) {
+        options.edits.push(Edit::LoadSnapshot);
+    }
+    if pressed.contains(&hotkeys.rewind) {
+        options.edits.push(Edit::Rewind);
+    }
+    if pressed.contains(&hotkeys.reset) {
+        options.edits.push(Edit::Reset);
+    }
+    // halves the speed each press, and doubles it back up to normal
+    if pressed.contains(&hotkeys.slow_motion) && options.time_scale > MIN_TIME_SCALE {
+        options.time_scale /= 2.0;
+    }
+    if pressed.contains(&hotkeys.normal_motion) && options.time_scale < 1.0 {
+        options.time_scale *= 2.0;
+    }
+    if pressed.contains(&hotkeys.pause_timers) {
+        options.timers_paused = !options.timers_paused;
+    }
+    if pressed.contains(&hotkeys.playlist_next) {
+        options.playlist_skip += 1;
+    }
+    if pressed.contains(&hotkeys.playlist_prev) {
+        options.playlist_skip -= 1;
+    }
     if !options.fast_forward {
-        if pressed.contains(&KeyCode::Up) {
-            options.instrs_per_second += 50.0;
-            options.instr_count = 0;
-            options.count_start = Instant::now();
+        if let Some(ipf) = options.instrs_per_frame {
+            if pressed.contains(&hotkeys.speed_up) {
+                options.set_instrs_per_frame(ipf + 1);
+                options.reset_instr_count();
+            }
+            if pressed.contains(&hotkeys.speed_down) && ipf > 1 {
+                options.set_instrs_per_frame(ipf - 1);
+                options.reset_instr_count();
+            }
+        } else {
+            if pressed.contains(&hotkeys.speed_up) {
+                options.instrs_per_second += 50.0;
+                options.reset_instr_count();
+            }
+            if pressed.contains(&hotkeys.speed_down) && options.instrs_per_second >= 50.0 {
+                options.instrs_per_second -= 50.0;
+                options.reset_instr_count();
+            }
         }
-        if pressed.contains(&KeyCode::Down) && options.instrs_per_second >= 50.0 {
-            options.instrs_per_second -= 50.0;
-            options.instr_count = 0;
-            options.count_start = Instant::now();
+    }
+}
+
+// a debugger command, a rom path, or hex bytes to run as a program
+fn run_command(line: &str, options: &Arc<Mutex<Shared>>) -> Result<String, String> {
+    if let Some(ips) = line.strip_prefix("speed ") {
+        return set_speed(ips.trim(), options);
+    }
+    if line == "finish" {
+        return finish(options);
+    }
+    let [b1, b2] = options.lock().unwrap().next_instr;
+    if let Some(edit) = parse_edit(line, Instr::new(b1, b2)) {
+        return queue_edit(edit?, options);
+    }
+    let path = Path::new(line);
+    let rom = if path.exists() {
+        Rom::load(path).map_err(|e| format!("could not read {line}: {e}"))?
+    } else {
+        Rom::from_hex(line).ok_or(format!("{line:?} is not a file or hex bytes"))?
+    };
+    let mut options = options.lock().unwrap();
+    // the compute thread checks too, this is just to say so here
+    let room = options.memory_len.saturating_sub(options.program_start);
+    if rom.bytes.len() > room {
+        return Err(format!(
+            "{} is {} bytes, only {room} fit",
+            rom.name,
+            rom.bytes.len()
+        ));
+    }
+    let text = format!("loaded {}", rom.name);
+    options.load_request = Some(rom);
+    Ok(text)
+}
+
+// runs until the subroutine the paused machine is in returns
+fn finish(options: &Arc<Mutex<Shared>>) -> Result<String, String> {
+    let mut options = options.lock().unwrap();
+    if !options.paused {
+        return Err("pause before running to a return".into());
+    }
+    if options.stack_depth == 0 {
+        return Err("not in a subroutine".into());
+    }
+    options.run_until = Some(RunUntil::Return);
+    Ok("running until the subroutine returns".into())
+}
+
+// "speed 1200" sets instructions per second, out of range values are clamped
+fn set_speed(ips: &str, options: &Arc<Mutex<Shared>>) -> Result<String, String> {
+    let ips = ips
+        .parse::<f64>()
+        .ok()
+        .filter(|ips| ips.is_finite())
+        .ok_or(format!(
+            "{ips:?} is not a number of instructions per second"
+        ))?
+        .clamp(MIN_SPEED, MAX_SPEED);
+    let mut options = options.lock().unwrap();
+    options.instrs_per_frame = None;
+    options.instrs_per_second = ips;
+    options.reset_instr_count();
+    Ok(format!("speed target: {ips} / sec"))
+}
+
+// "warp 0x2A0", "V3 = 0x20", "[0x300] = 0xFF", "sprite 5", "exec 00E0" or
+// "diff", None if it's none of those
+fn parse_edit(line: &str, next_instr: Instr) -> Option<Result<Edit, String>> {
+    let compact = line.replace(' ', "");
+    let sprite = tern!(line == "sprite", Some(""), line.strip_prefix("sprite "));
+    let edit = if line == "diff" {
+        Ok(Edit::DiffSnapshot)
+    } else if let Some(rows) = sprite {
+        // without a height, the one the next DXYN would draw
+        let rows = match (rows.trim(), next_instr.as_nibbles()) {
+            ("", (0xD, _, _, n)) => Some(n),
+            (rows, _) => rows.parse().ok(),
+        };
+        rows.filter(|rows| (1..=0xF).contains(rows))
+            .map(Edit::DumpSprite)
+            .ok_or("sprite expects a height from 1 to 15".into())
+    } else if let Some(hex) = line.strip_prefix("exec ") {
+        Rom::from_hex(hex.trim())
+            .and_then(|rom| <[u8; 2]>::try_from(rom.bytes).ok())
+            .map(|[b1, b2]| Edit::Execute(b1, b2))
+            .ok_or(format!("{hex:?} is not one instruction like 00E0"))
+    } else if let Some(args) = line.strip_prefix("dis ") {
+        // a length in bytes, 32 if left out
+        let mut args = args.split_whitespace().map(parse_number);
+        match (args.next().flatten(), args.next().unwrap_or(Some(32))) {
+            (Some(addr), Some(len)) if len > 0 => Ok(Edit::Disassemble(addr, len)),
+            _ => Err("dis expects an address and optionally a length".into()),
         }
+    } else if let Some(addr) = line.strip_prefix("warp ") {
+        parse_number(addr.trim())
+            .map(Edit::Warp)
+            .ok_or(format!("{addr:?} is not an address"))
+    } else if compact.starts_with(['V', 'v']) && compact.contains('=') {
+        parse_register_assignment(&compact)
+            .map(|(x, v)| Edit::SetRegister(x, v))
+            .ok_or(format!("{line:?} should look like V3 = 0x20"))
+    } else if let Some(rest) = compact.strip_prefix('[') {
+        parse_poke(&rest.replacen(']', "", 1))
+            .map(|(addr, v)| Edit::Poke(addr, v))
+            .ok_or(format!("{line:?} should look like [0x300] = 0xFF"))
+    } else {
+        return None;
+    };
+    Some(edit)
+}
+
+fn queue_edit(edit: Edit, options: &Arc<Mutex<Shared>>) -> Result<String, String> {
+    let mut options = options.lock().unwrap();
+    if !options.paused {
+        return Err("pause before changing the machine".into());
     }
+    // the last byte each edit touches, a warp needs a whole instruction
+    let last = match edit {
+        Edit::Warp(addr) => Some(addr + 1),
+        Edit::Poke(addr, _) => Some(addr),
+        Edit::Disassemble(addr, len) => Some(addr + len - 1),
+        Edit::SetRegister(..)
+        | Edit::ToggleQuirk(_)
+        | Edit::DumpSprite(_)
+        | Edit::Execute(..)
+        | Edit::SaveSnapshot
+        | Edit::LoadSnapshot
+        | Edit::DiffSnapshot
+        | Edit::Rewind
+        | Edit::Reset => None,
+    };
+    if let Some(last) = last
+        && last >= options.memory_len
+    {
+        return Err(format!(
+            "{last:#05X} is past the end of memory ({:#05X})",
+            options.memory_len
+        ));
+    }
+    let text = match edit {
+        Edit::Warp(addr) => format!("pc = {addr:#05X}"),
+        Edit::SetRegister(x, v) => format!("V{x:X} = {v:#04X}"),
+        Edit::Poke(addr, v) => format!("[{addr:#05X}] = {v:#04X}"),
+        Edit::ToggleQuirk(quirk) => format!("toggled {}", quirk.name()),
+        Edit::DumpSprite(rows) => format!("printed {rows} rows of sprite data"),
+        Edit::Disassemble(addr, len) => format!("printed {len} bytes from {addr:#05X}"),
+        Edit::Execute(b1, b2) => format!("ran {}", Instr::new(b1, b2).disassemble()),
+        Edit::SaveSnapshot => "saved a snapshot".into(),
+        Edit::LoadSnapshot => "loaded the snapshot".into(),
+        Edit::DiffSnapshot => "printed the changes since the snapshot".into(),
+        Edit::Rewind => "rewound a frame".into(),
+        Edit::Reset => "reset the machine".into(),
+    };
+    options.edits.push(edit);
+    Ok(text)
+}
+
+// falls back to stdout when there's no clipboard to write to
+fn copy_screen(screen: &Screen) {
+    let text = screen.to_ascii();
+    miniquad::window::clipboard_set(&text);
+    if miniquad::window::clipboard_get().as_deref() == Some(text.as_str()) {
+        println!("copied screen to clipboard");
+    } else {
+        println!("clipboard unavailable, screen follows:\n{text}");
+    }
+}
+
+fn keypad_mask(key_map: &[KeyCode; 0x10], down: &HashSet<KeyCode>) -> u16 {
+    key_map
+        .iter()
+        .enumerate()
+        .filter(|(_, code)| down.contains(code))
+        .fold(0, |mask, (key, _)| mask | 1 << key)
 }